@@ -9,18 +9,39 @@ struct Dial {
     maximum: i32,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 enum Direction {
     Left,
     Right,
 }
 
-impl From<char> for Direction {
-    fn from(c: char) -> Self {
+#[derive(Debug, PartialEq)]
+enum ParseError {
+    Empty,
+    InvalidDirection(char),
+    InvalidSteps(String),
+}
+
+impl std::fmt::Display for ParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            ParseError::Empty => write!(f, "empty line"),
+            ParseError::InvalidDirection(c) => write!(f, "invalid direction: {c:?}"),
+            ParseError::InvalidSteps(steps) => write!(f, "invalid number of steps: {steps:?}"),
+        }
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+impl TryFrom<char> for Direction {
+    type Error = ParseError;
+
+    fn try_from(c: char) -> Result<Self, Self::Error> {
         match c {
-            'L' => Direction::Left,
-            'R' => Direction::Right,
-            _ => panic!("Invalid direction: {c}"),
+            'L' => Ok(Direction::Left),
+            'R' => Ok(Direction::Right),
+            _ => Err(ParseError::InvalidDirection(c)),
         }
     }
 }
@@ -62,6 +83,8 @@ impl Dial {
         self.position = position;
     }
 
+    /// Reference implementation of `step`, moving one click at a time
+    #[cfg(test)]
     fn step_with_for_loop(&mut self, direction: Direction, steps: i32) {
         println!(
             "Current position: {}, moving {direction:?} by {steps} steps",
@@ -98,62 +121,19 @@ impl Dial {
 
         // every time we move, we need to check if we cross past 0 and increment the counter each time
         // or if we end at 0, increment the counter
+        self.counter += self.count_zero_hits(direction, steps);
 
         // to calculate where the position will land
         let absolute_steps = steps % self.get_range();
 
-        let new_position: i32;
-        match direction {
-            Direction::Left => {
-                // calculate new position
-                new_position = self.position - absolute_steps;
-                println!(
-                    "    Calculated new position (before wrap): {}",
-                    new_position
-                );
-            }
-            Direction::Right => {
-                new_position = self.position + absolute_steps;
-                println!(
-                    "    Calculated new position (before wrap): {}",
-                    new_position
-                );
-            }
-        }
-
-        // calculate how many 0s are between the old position and the new position
-        match direction {
-            Direction::Left => {
-                if new_position < self.minimum {
-                    // wrapped around
-                    let distance_to_min = self.position - self.minimum;
-                    let distance_beyond_min = absolute_steps - distance_to_min;
-                    let full_wraps = distance_beyond_min / self.get_range();
-                    let remainder = distance_beyond_min % self.get_range();
-                    self.counter += 1 + full_wraps;
-                    if remainder == 0 {
-                        self.counter -= 1; // ended exactly on 0
-                    }
-                } else if new_position == 0 {
-                    self.counter += 1;
-                }
-            }
-            Direction::Right => {
-                if new_position > self.maximum {
-                    // wrapped around
-                    let distance_to_max = self.maximum - self.position;
-                    let distance_beyond_max = absolute_steps - distance_to_max;
-                    let full_wraps = distance_beyond_max / self.get_range();
-                    let remainder = distance_beyond_max % self.get_range();
-                    self.counter += 1 + full_wraps;
-                    if remainder == 0 {
-                        self.counter -= 1; // ended exactly on 0
-                    }
-                } else if new_position == 0 {
-                    self.counter += 1;
-                }
-            }
-        }
+        let new_position = match direction {
+            Direction::Left => self.position - absolute_steps,
+            Direction::Right => self.position + absolute_steps,
+        };
+        println!(
+            "    Calculated new position (before wrap): {}",
+            new_position
+        );
 
         self.set_position(
             ((new_position - self.minimum).rem_euclid(self.get_range())) + self.minimum,
@@ -165,10 +145,41 @@ impl Dial {
         );
     }
 
-    fn parse_line(line: &str) -> (Direction, i32) {
-        let (direction, steps_str) = line.split_at(1);
-        let steps: i32 = steps_str.parse().unwrap();
-        (Direction::from(direction.chars().next().unwrap()), steps)
+    /// Count how many of the next `steps` clicks in `direction` land on 0
+    fn count_zero_hits(&self, direction: Direction, steps: i32) -> i32 {
+        if steps <= 0 || self.minimum > 0 || self.maximum < 0 {
+            return 0;
+        }
+        let range = self.get_range();
+
+        // number of clicks until we first land on 0
+        let first_hit = match direction {
+            Direction::Left => self.position.rem_euclid(range),
+            Direction::Right => (-self.position).rem_euclid(range),
+        };
+        // if we're already on 0, we need a full turn to get back to it
+        let first_hit = if first_hit == 0 { range } else { first_hit };
+
+        if steps < first_hit {
+            return 0;
+        }
+        // after the first hit, we land on 0 again every full turn
+        1 + (steps - first_hit) / range
+    }
+
+    /// Parse an instruction like `L68`: a direction followed by a non-negative number of steps
+    fn parse_line(line: &str) -> Result<(Direction, i32), ParseError> {
+        let mut chars = line.chars();
+        let direction = Direction::try_from(chars.next().ok_or(ParseError::Empty)?)?;
+        let steps_str = chars.as_str();
+        // only plain digits, so signs can't sneak in a negative step count
+        if steps_str.is_empty() || !steps_str.bytes().all(|b| b.is_ascii_digit()) {
+            return Err(ParseError::InvalidSteps(steps_str.to_string()));
+        }
+        let steps: i32 = steps_str
+            .parse()
+            .map_err(|_| ParseError::InvalidSteps(steps_str.to_string()))?;
+        Ok((direction, steps))
     }
 }
 
@@ -180,8 +191,9 @@ fn main() {
         if line.trim().is_empty() {
             continue;
         }
-        let (direction, steps_str) = Dial::parse_line(line);
-        dial.step_with_for_loop(direction, steps_str);
+        let (direction, steps_str) =
+            Dial::parse_line(line).unwrap_or_else(|e| panic!("Invalid instruction '{line}': {e}"));
+        dial.step(direction, steps_str);
     }
 
     println!("Final position: {}", dial.position);
@@ -197,7 +209,7 @@ mod tests {
         const EXAMPLE_INPUT: &str = include_str!("../data/example.txt");
         let mut dial = Dial::default();
         for line in EXAMPLE_INPUT.lines() {
-            let (direction, steps_str) = Dial::parse_line(line);
+            let (direction, steps_str) = Dial::parse_line(line).unwrap();
             dial.step(direction, steps_str);
         }
         assert_eq!(dial.position, 32);
@@ -212,7 +224,7 @@ mod tests {
         const EXAMPLE_INPUT: &str = include_str!("../data/example.txt");
         let mut dial = Dial::default();
         for line in EXAMPLE_INPUT.lines() {
-            let (direction, steps_str) = Dial::parse_line(line);
+            let (direction, steps_str) = Dial::parse_line(line).unwrap();
             dial.step_with_for_loop(direction, steps_str);
         }
         assert_eq!(dial.position, 32);
@@ -231,7 +243,7 @@ mod tests {
             if line.trim().is_empty() {
                 continue;
             }
-            let (direction, steps_str) = Dial::parse_line(line);
+            let (direction, steps_str) = Dial::parse_line(line).unwrap();
             dial.step(direction, steps_str);
         }
         assert_eq!(dial.position, 68);
@@ -247,7 +259,7 @@ mod tests {
             if line.trim().is_empty() {
                 continue;
             }
-            let (direction, steps_str) = Dial::parse_line(line);
+            let (direction, steps_str) = Dial::parse_line(line).unwrap();
             dial.step_with_for_loop(direction, steps_str);
         }
         assert_eq!(dial.position, 68);
@@ -400,4 +412,199 @@ mod tests {
         assert_eq!(dial.position, 97);
         assert_eq!(dial.counter, 15); // crossed 0 4 times more (at 100, 200, 300, 400)
     }
+
+    /// Xorshift generator for the random dials and instruction lines, seeded per test so a failing
+    /// scenario comes back the same on every run
+    struct Rng(u64);
+
+    impl Rng {
+        fn next_u64(&mut self) -> u64 {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 7;
+            self.0 ^= self.0 << 17;
+            self.0
+        }
+
+        /// Random number in the inclusive range [low, high]
+        fn range(&mut self, low: i32, high: i32) -> i32 {
+            let span = (high as i64 - low as i64 + 1) as u64;
+            (low as i64 + (self.next_u64() % span) as i64) as i32
+        }
+    }
+
+    #[derive(Debug, Clone)]
+    struct Scenario {
+        minimum: i32,
+        maximum: i32,
+        start: i32,
+        instructions: Vec<(Direction, i32)>,
+    }
+
+    impl Scenario {
+        fn generate(rng: &mut Rng) -> Self {
+            let minimum = rng.range(-50, 50);
+            let maximum = minimum + rng.range(0, 150);
+            let start = rng.range(minimum, maximum);
+            let instructions = (0..rng.range(0, 30))
+                .map(|_| {
+                    let direction = if rng.range(0, 1) == 0 {
+                        Direction::Left
+                    } else {
+                        Direction::Right
+                    };
+                    (direction, rng.range(0, 1000))
+                })
+                .collect();
+            Scenario {
+                minimum,
+                maximum,
+                start,
+                instructions,
+            }
+        }
+
+        /// Run both strategies side by side, returning the first instruction where they disagree
+        fn find_mismatch(&self) -> Option<String> {
+            let mut dial = Dial::new(self.minimum, self.maximum, self.start);
+            let mut reference = Dial::new(self.minimum, self.maximum, self.start);
+            for (i, &(direction, steps)) in self.instructions.iter().enumerate() {
+                dial.step(direction, steps);
+                reference.step_with_for_loop(direction, steps);
+                if (dial.position, dial.counter) != (reference.position, reference.counter) {
+                    return Some(format!(
+                        "instruction {i}: step gave (position {}, counter {}), for loop gave (position {}, counter {})",
+                        dial.position, dial.counter, reference.position, reference.counter,
+                    ));
+                }
+            }
+            None
+        }
+
+        /// Smaller variants of this scenario, simplest first
+        fn shrink(&self) -> Vec<Scenario> {
+            let mut candidates = Vec::new();
+            for i in 0..self.instructions.len() {
+                let mut smaller = self.clone();
+                smaller.instructions.remove(i);
+                candidates.push(smaller);
+            }
+            for i in 0..self.instructions.len() {
+                let steps = self.instructions[i].1;
+                for smaller_steps in [steps / 2, steps - 1] {
+                    if smaller_steps >= 0 && smaller_steps < steps {
+                        let mut smaller = self.clone();
+                        smaller.instructions[i].1 = smaller_steps;
+                        candidates.push(smaller);
+                    }
+                }
+            }
+            if self.maximum > self.start {
+                candidates.push(Scenario {
+                    maximum: self.maximum - 1,
+                    ..self.clone()
+                });
+            }
+            if self.minimum < self.start {
+                candidates.push(Scenario {
+                    minimum: self.minimum + 1,
+                    ..self.clone()
+                });
+            }
+            candidates
+        }
+    }
+
+    /// Keep replacing a failing scenario with a smaller failing one until none is left
+    fn shrink_to_minimal(mut scenario: Scenario, fails: impl Fn(&Scenario) -> bool) -> Scenario {
+        while let Some(smaller) = scenario.shrink().into_iter().find(&fails) {
+            scenario = smaller;
+        }
+        scenario
+    }
+
+    #[test]
+    fn test_step_matches_for_loop() {
+        let mut rng = Rng(0x5eed_da7a_d1a1_0001);
+        for _ in 0..500 {
+            let scenario = Scenario::generate(&mut rng);
+            if scenario.find_mismatch().is_some() {
+                let minimal = shrink_to_minimal(scenario, |s| s.find_mismatch().is_some());
+                panic!(
+                    "step and step_with_for_loop disagree on {minimal:?}: {}",
+                    minimal.find_mismatch().unwrap(),
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_shrinking_finds_minimal_scenario() {
+        // failing whenever a full turn of 100 steps or more shows up, which takes a single instruction
+        // of exactly 100 steps once shrunk
+        let scenario = Scenario {
+            minimum: -20,
+            maximum: 80,
+            start: 30,
+            instructions: vec![
+                (Direction::Right, 5),
+                (Direction::Left, 400),
+                (Direction::Right, 12),
+            ],
+        };
+        let minimal = shrink_to_minimal(scenario, |s| {
+            s.instructions.iter().any(|&(_, steps)| steps >= 100)
+        });
+        assert_eq!(minimal.instructions.len(), 1);
+        assert_eq!(minimal.instructions[0].1, 100);
+    }
+
+    #[test]
+    fn test_parse_line_errors() {
+        assert_eq!(Dial::parse_line(""), Err(ParseError::Empty));
+        assert_eq!(
+            Dial::parse_line("X10"),
+            Err(ParseError::InvalidDirection('X'))
+        );
+        assert_eq!(
+            Dial::parse_line("é10"),
+            Err(ParseError::InvalidDirection('é'))
+        );
+        assert_eq!(
+            Dial::parse_line("L"),
+            Err(ParseError::InvalidSteps(String::new()))
+        );
+        assert_eq!(
+            Dial::parse_line("R-5"),
+            Err(ParseError::InvalidSteps("-5".to_string()))
+        );
+        assert_eq!(
+            Dial::parse_line("R99999999999"),
+            Err(ParseError::InvalidSteps("99999999999".to_string()))
+        );
+        assert_eq!(
+            Dial::parse_line("R2147483647"),
+            Ok((Direction::Right, i32::MAX))
+        );
+    }
+
+    #[test]
+    fn test_fuzz_parse_line() {
+        // mostly directions and digits so lines often parse, with signs, spaces and non-ASCII
+        // characters to hit every error
+        const ALPHABET: &[char] = &['L', 'R', '0', '1', '9', '-', '+', ' ', 'x', 'é', '\0'];
+        let mut rng = Rng(0xf022_da7a_d1a1_0002);
+        for _ in 0..20_000 {
+            let length = rng.range(0, 24);
+            let line: String = (0..length)
+                .map(|_| ALPHABET[rng.range(0, ALPHABET.len() as i32 - 1) as usize])
+                .collect();
+            // the parser must never panic, and every instruction it accepts must be safe to apply
+            if let Ok((direction, steps)) = Dial::parse_line(&line) {
+                assert!(steps >= 0, "accepted negative steps from {line:?}");
+                let mut dial = Dial::default();
+                dial.step(direction, steps);
+                assert!((DIAL_MIN..=DIAL_MAX).contains(&dial.position));
+            }
+        }
+    }
 }
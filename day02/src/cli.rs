@@ -0,0 +1,292 @@
+use crate::export::ExportFormat;
+use crate::report::Format;
+use crate::serve::Address;
+use std::path::PathBuf;
+
+/// Options for finding invalid IDs, shared by the commands that scan the puzzle input
+#[derive(Debug, PartialEq)]
+pub struct ScanOptions {
+    pub threads: usize,
+    /// Rule file to use instead of the puzzle's repeated sequence rule
+    pub rules: Option<PathBuf>,
+}
+
+impl Default for ScanOptions {
+    fn default() -> Self {
+        ScanOptions {
+            threads: std::thread::available_parallelism().map_or(1, |threads| threads.get()),
+            rules: None,
+        }
+    }
+}
+
+/// What to do, picked by the first command line argument
+#[derive(Debug, PartialEq)]
+pub enum Command {
+    /// Find the invalid IDs in the puzzle input
+    Scan(ScanOptions),
+    /// Break the invalid IDs down per input range
+    Report {
+        scan: ScanOptions,
+        format: Format,
+        /// Most invalid IDs to list per range
+        limit: usize,
+    },
+    /// Suggest the nearest valid IDs for a list of IDs, read from a file or stdin
+    Suggest { input: Option<PathBuf> },
+    /// Stream every invalid ID in ascending order to a file or stdout
+    Export {
+        rules: Option<PathBuf>,
+        format: ExportFormat,
+        output: Option<PathBuf>,
+    },
+    /// Answer validity requests from other programs over a local socket
+    Serve(Address),
+}
+
+pub const USAGE: &str = "Usage:
+  day02 [scan] [--threads <count>] [--rules <file>]
+  day02 report [--format text|csv|json] [--limit <count>] [--threads <count>] [--rules <file>]
+  day02 suggest [<file>]
+  day02 export [--format text|varint] [--output <file>] [--rules <file>]
+  day02 serve --tcp <port> | --unix <path>";
+
+/// Take the value following an option
+fn value(option: &str, args: &mut impl Iterator<Item = String>) -> Result<String, String> {
+    args.next().ok_or_else(|| format!("{option} needs a value"))
+}
+
+impl Command {
+    /// Parse the arguments following the program name
+    pub fn parse(args: impl IntoIterator<Item = String>) -> Result<Self, String> {
+        let mut args = args.into_iter().peekable();
+        match args.peek().map(String::as_str) {
+            Some("suggest") => {
+                args.next();
+                let input = args.next().map(PathBuf::from);
+                if let Some(arg) = args.next() {
+                    return Err(format!("Unknown argument: {arg:?}"));
+                }
+                Ok(Command::Suggest { input })
+            }
+            Some("export") => {
+                args.next();
+                let mut rules = None;
+                let mut format = ExportFormat::Text;
+                let mut output = None;
+                while let Some(arg) = args.next() {
+                    match arg.as_str() {
+                        "--format" => format = value(&arg, &mut args)?.parse()?,
+                        "--output" => output = Some(PathBuf::from(value(&arg, &mut args)?)),
+                        "--rules" => rules = Some(PathBuf::from(value(&arg, &mut args)?)),
+                        _ => return Err(format!("Unknown argument: {arg:?}")),
+                    }
+                }
+                Ok(Command::Export {
+                    rules,
+                    format,
+                    output,
+                })
+            }
+            Some("serve") => {
+                args.next();
+                let option = args.next().ok_or("serve needs --tcp or --unix")?;
+                let address = match option.as_str() {
+                    "--tcp" => {
+                        let port = value(&option, &mut args)?;
+                        Address::Tcp(
+                            port.parse()
+                                .map_err(|_| format!("Invalid port: {port:?}"))?,
+                        )
+                    }
+                    #[cfg(unix)]
+                    "--unix" => Address::Unix(PathBuf::from(value(&option, &mut args)?)),
+                    _ => return Err(format!("Unknown argument: {option:?}")),
+                };
+                if let Some(arg) = args.next() {
+                    return Err(format!("Unknown argument: {arg:?}"));
+                }
+                Ok(Command::Serve(address))
+            }
+            Some("report") => {
+                args.next();
+                let mut scan = ScanOptions::default();
+                let mut format = Format::Text;
+                let mut limit = 10;
+                while let Some(arg) = args.next() {
+                    match arg.as_str() {
+                        "--format" => format = value(&arg, &mut args)?.parse()?,
+                        "--limit" => {
+                            let limit_value = value(&arg, &mut args)?;
+                            limit = limit_value
+                                .parse()
+                                .map_err(|_| format!("Invalid limit: {limit_value:?}"))?;
+                        }
+                        _ => scan.parse_option(&arg, &mut args)?,
+                    }
+                }
+                Ok(Command::Report {
+                    scan,
+                    format,
+                    limit,
+                })
+            }
+            Some("scan") => {
+                args.next();
+                Self::parse_scan(args)
+            }
+            _ => Self::parse_scan(args),
+        }
+    }
+
+    fn parse_scan(mut args: impl Iterator<Item = String>) -> Result<Self, String> {
+        let mut scan = ScanOptions::default();
+        while let Some(arg) = args.next() {
+            scan.parse_option(&arg, &mut args)?;
+        }
+        Ok(Command::Scan(scan))
+    }
+}
+
+impl ScanOptions {
+    /// Parse a single option, taking its value from `args`
+    fn parse_option(
+        &mut self,
+        arg: &str,
+        args: &mut impl Iterator<Item = String>,
+    ) -> Result<(), String> {
+        match arg {
+            "--threads" => {
+                let threads = value(arg, args)?;
+                self.threads = threads
+                    .parse()
+                    .ok()
+                    .filter(|&threads| threads > 0)
+                    .ok_or_else(|| format!("Invalid thread count: {threads:?}"))?;
+            }
+            "--rules" => self.rules = Some(PathBuf::from(value(arg, args)?)),
+            _ => return Err(format!("Unknown argument: {arg:?}")),
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<Command, String> {
+        Command::parse(args.iter().map(|arg| arg.to_string()))
+    }
+
+    #[test]
+    fn test_parse_scan() {
+        assert_eq!(parse(&[]), Ok(Command::Scan(ScanOptions::default())));
+        assert_eq!(
+            parse(&["--threads", "4"]),
+            Ok(Command::Scan(ScanOptions {
+                threads: 4,
+                rules: None
+            }))
+        );
+        assert_eq!(
+            parse(&["scan", "--rules", "rules.txt", "--threads", "2"]),
+            Ok(Command::Scan(ScanOptions {
+                threads: 2,
+                rules: Some(PathBuf::from("rules.txt"))
+            }))
+        );
+        assert!(parse(&["--rules"]).is_err());
+        assert!(parse(&["--threads"]).is_err());
+        assert!(parse(&["--threads", "0"]).is_err());
+        assert!(parse(&["--threads", "many"]).is_err());
+        assert!(parse(&["--verbose"]).is_err());
+    }
+
+    #[test]
+    fn test_parse_report() {
+        assert_eq!(
+            parse(&["report"]),
+            Ok(Command::Report {
+                scan: ScanOptions::default(),
+                format: Format::Text,
+                limit: 10
+            })
+        );
+        assert_eq!(
+            parse(&[
+                "report",
+                "--format",
+                "json",
+                "--limit",
+                "3",
+                "--threads",
+                "2"
+            ]),
+            Ok(Command::Report {
+                scan: ScanOptions {
+                    threads: 2,
+                    rules: None
+                },
+                format: Format::Json,
+                limit: 3
+            })
+        );
+        assert!(parse(&["report", "--format", "xml"]).is_err());
+        assert!(parse(&["report", "--limit", "-1"]).is_err());
+    }
+
+    #[test]
+    fn test_parse_suggest() {
+        assert_eq!(parse(&["suggest"]), Ok(Command::Suggest { input: None }));
+        assert_eq!(
+            parse(&["suggest", "ids.txt"]),
+            Ok(Command::Suggest {
+                input: Some(PathBuf::from("ids.txt"))
+            })
+        );
+        assert!(parse(&["suggest", "a.txt", "b.txt"]).is_err());
+    }
+
+    #[test]
+    fn test_parse_export() {
+        assert_eq!(
+            parse(&["export"]),
+            Ok(Command::Export {
+                rules: None,
+                format: ExportFormat::Text,
+                output: None
+            })
+        );
+        assert_eq!(
+            parse(&["export", "--output", "ids.bin", "--format", "varint"]),
+            Ok(Command::Export {
+                rules: None,
+                format: ExportFormat::Varint,
+                output: Some(PathBuf::from("ids.bin"))
+            })
+        );
+        assert!(parse(&["export", "--format", "csv"]).is_err());
+        // exporting streams the IDs in order, so it runs on a single thread
+        assert!(parse(&["export", "--threads", "2"]).is_err());
+        assert!(parse(&["export", "--output"]).is_err());
+    }
+
+    #[test]
+    fn test_parse_serve() {
+        assert_eq!(
+            parse(&["serve", "--tcp", "7878"]),
+            Ok(Command::Serve(Address::Tcp(7878)))
+        );
+        #[cfg(unix)]
+        assert_eq!(
+            parse(&["serve", "--unix", "/tmp/day02.sock"]),
+            Ok(Command::Serve(Address::Unix(PathBuf::from(
+                "/tmp/day02.sock"
+            ))))
+        );
+        assert!(parse(&["serve"]).is_err());
+        assert!(parse(&["serve", "--tcp", "70000"]).is_err());
+        assert!(parse(&["serve", "--tcp", "1", "--unix", "a.sock"]).is_err());
+    }
+}
@@ -0,0 +1,258 @@
+//! Counting invalid IDs in a range without enumerating every ID in it.
+//!
+//! In base 10, an `n`-digit ID made of a `d`-digit block repeated `n / d` times is `block * (10^(n-d) + ... + 10^d + 1)`,
+//! so for a fixed period the invalid IDs in a range are a run of consecutive blocks, which we can count
+//! and sum directly. An ID with period `d` also has every period that is a multiple of `d`, so the
+//! periods are combined with inclusion-exclusion (Möbius function over the divisors of `n`) to count
+//! every invalid ID exactly once.
+
+/// Number of invalid IDs in the inclusive range [start, end], with IDs written in `radix`
+pub fn count_invalid(start: u64, end: u64, radix: u32) -> u64 {
+    count_and_sum(start, end, radix).0
+}
+
+/// Sum of the invalid IDs in the inclusive range [start, end], with IDs written in `radix`
+pub fn sum_invalid(start: u64, end: u64, radix: u32) -> u128 {
+    count_and_sum(start, end, radix).1
+}
+
+/// Number of invalid IDs in the inclusive range [start, end] for IDs of any size, which is as far as
+/// it goes as their sum could overflow
+pub fn count_invalid_wide(start: u128, end: u128, radix: u32) -> u128 {
+    repeated_blocks(start, end, radix)
+        .into_iter()
+        .map(|(sign, blocks)| sign * blocks.count() as i128)
+        .sum::<i128>() as u128
+}
+
+fn count_and_sum(start: u64, end: u64, radix: u32) -> (u64, u128) {
+    let mut count: i128 = 0;
+    let mut sum: i128 = 0;
+    for (sign, blocks) in repeated_blocks(start.into(), end.into(), radix) {
+        count += sign * blocks.count() as i128;
+        // the sum of u64 IDs always fits
+        sum += sign * blocks.sum() as i128;
+    }
+    (count as u64, sum as u128)
+}
+
+/// The IDs made of a repeated block in a range, each of them `block * multiplier` for a block in
+/// [first, last]
+struct Blocks {
+    first: u128,
+    last: u128,
+    multiplier: u128,
+}
+
+impl Blocks {
+    fn count(&self) -> u128 {
+        self.last - self.first + 1
+    }
+
+    fn sum(&self) -> u128 {
+        (self.first + self.last) * self.count() / 2 * self.multiplier
+    }
+}
+
+/// The runs of repeated blocks for every digit count and period that make up the invalid IDs in
+/// [start, end], with the sign they're counted with
+fn repeated_blocks(start: u128, end: u128, radix: u32) -> Vec<(i128, Blocks)> {
+    assert!((2..=36).contains(&radix), "Invalid radix: {radix}");
+    let radix = radix as u128;
+    let mut runs = Vec::new();
+    if start > end {
+        return runs;
+    }
+
+    // most digits a u128 can have in this radix
+    let max_digits = u128::MAX.ilog(radix) + 1;
+    // single digit IDs are always valid
+    for digits in 2..=max_digits {
+        // clamp the range to the IDs that have exactly `digits` digits
+        let low = start.max(radix.pow(digits - 1));
+        let high = end.min(
+            radix
+                .checked_pow(digits)
+                .map_or(u128::MAX, |power| power - 1),
+        );
+        if low > high {
+            continue;
+        }
+        for period in (1..digits).filter(|&period| digits.is_multiple_of(period)) {
+            let sign = -mobius(digits / period);
+            if sign == 0 {
+                continue;
+            }
+            if let Some(blocks) = blocks_in(low, high, radix, digits, period) {
+                runs.push((sign, blocks));
+            }
+        }
+    }
+    runs
+}
+
+/// The `digits`-digit IDs in [low, high] made of a repeated `period`-digit block
+fn blocks_in(low: u128, high: u128, radix: u128, digits: u32, period: u32) -> Option<Blocks> {
+    // 1 + radix^period + radix^(2*period) + ... turns a block into the full ID, added up term by
+    // term as radix^digits itself might not fit
+    let multiplier = (0..digits / period)
+        .map(|repetition| radix.pow(repetition * period))
+        .sum();
+
+    // blocks can't have leading zeros, and must land the ID inside the range
+    let first = radix.pow(period - 1).max(low.div_ceil(multiplier));
+    let last = (radix.pow(period) - 1).min(high / multiplier);
+    (first <= last).then_some(Blocks {
+        first,
+        last,
+        multiplier,
+    })
+}
+
+/// The Möbius function: 0 if `n` has a squared prime factor, otherwise -1 or 1 for an odd or even number of prime factors
+fn mobius(mut n: u32) -> i128 {
+    let mut result = 1;
+    let mut factor = 2;
+    while factor * factor <= n {
+        if n.is_multiple_of(factor) {
+            n /= factor;
+            if n.is_multiple_of(factor) {
+                return 0;
+            }
+            result = -result;
+        }
+        factor += 1;
+    }
+    if n > 1 {
+        result = -result;
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Id;
+
+    fn brute_force(start: u64, end: u64, radix: u32) -> (u64, u128) {
+        (start..=end)
+            .filter(|&value| !Id::new(value as u128).in_radix(radix).is_valid())
+            .fold((0, 0), |(count, sum), value| {
+                (count + 1, sum + value as u128)
+            })
+    }
+
+    #[test]
+    fn test_mobius() {
+        let expected = [1, -1, -1, 0, -1, 1, -1, 0, 0, 1, -1, 0];
+        for (n, mu) in (1..=12).zip(expected) {
+            assert_eq!(mobius(n), mu, "mobius({n})");
+        }
+    }
+
+    #[test]
+    fn test_matches_brute_force() {
+        for (start, end) in [
+            (0, 10_000),
+            (11, 22),
+            (95, 115),
+            (998, 1012),
+            (222220, 222224),
+            (100_000, 1_200_000),
+            (1188511880, 1188511890),
+            (2121212118, 2121212124),
+            (50, 40),
+        ] {
+            assert_eq!(
+                (count_invalid(start, end, 10), sum_invalid(start, end, 10)),
+                brute_force(start, end, 10),
+                "range {start}-{end}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_matches_brute_force_in_other_radixes() {
+        for radix in [2, 3, 7, 16, 36] {
+            for (start, end) in [(0, 5_000), (123_456, 140_000)] {
+                assert_eq!(
+                    (
+                        count_invalid(start, end, radix),
+                        sum_invalid(start, end, radix)
+                    ),
+                    brute_force(start, end, radix),
+                    "range {start}-{end} in radix {radix}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_full_input() {
+        let id_ranges = crate::parse_ranges(include_str!("../data/input.txt")).unwrap();
+        let range_set = crate::RangeSet::new(id_ranges);
+        let (invalid_count, invalid_sum) = range_set
+            .ranges()
+            .iter()
+            .map(|range| {
+                let start = range.start.try_into().unwrap();
+                let end = range.end.try_into().unwrap();
+                (
+                    count_invalid(start, end, range.radix),
+                    sum_invalid(start, end, range.radix),
+                )
+            })
+            .fold((0, 0), |(count, sum), (range_count, range_sum)| {
+                (count + range_count, sum + range_sum)
+            });
+        assert_eq!(invalid_sum, 33832678380);
+        // the same as scanning every ID
+        let summary = crate::parallel::summarize(&range_set, 4, &crate::rules::Repeated);
+        assert_eq!(
+            (invalid_count, invalid_sum),
+            (summary.count(), summary.sum())
+        );
+    }
+
+    #[test]
+    fn test_count_invalid_wide() {
+        for radix in [2, 10, 36] {
+            for (start, end) in [(0, 5_000), (u64::MAX - 5_000, u64::MAX)] {
+                assert_eq!(
+                    count_invalid_wide(start.into(), end.into(), radix),
+                    count_invalid(start, end, radix).into()
+                );
+            }
+        }
+        // 128 bits, all set and then "10" repeated
+        assert_eq!(count_invalid_wide(u128::MAX - 1, u128::MAX, 2), 1);
+        let alternating = u128::MAX / 3 * 2;
+        assert_eq!(count_invalid_wide(alternating, alternating, 2), 1);
+    }
+
+    #[test]
+    fn test_near_u64_max() {
+        // the largest invalid ID a u64 can hold is "1844674407" repeated twice
+        let largest_invalid = 18446744071844674407;
+        assert_eq!(count_invalid(largest_invalid, u64::MAX, 10), 1);
+        assert_eq!(
+            sum_invalid(largest_invalid, u64::MAX, 10),
+            largest_invalid as u128
+        );
+        assert_eq!(count_invalid(largest_invalid + 1, u64::MAX, 10), 0);
+
+        // 20 digit IDs can have period 10 (844674408 blocks up to 1844674407), 4 (845 blocks),
+        // and 5, 2 or 1, which are all already covered by periods 10 and 4, except for
+        // period 2 (9 blocks) which both of them count
+        assert_eq!(
+            count_invalid(10u64.pow(19), u64::MAX, 10),
+            844674408 + 845 - 9
+        );
+        assert!(sum_invalid(0, u64::MAX, 10) > u64::MAX as u128);
+
+        // in binary, u64::MAX itself is "1" repeated 64 times
+        assert_eq!(count_invalid(u64::MAX, u64::MAX, 2), 1);
+        assert_eq!(count_invalid(u64::MAX - 1, u64::MAX - 1, 36), 0);
+        assert!(sum_invalid(0, u64::MAX, 36) > 0);
+    }
+}
@@ -0,0 +1,209 @@
+use crate::Id;
+use crate::range_set::RangeSet;
+use crate::rules::Rule;
+use std::io::{self, Write};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ExportFormat {
+    /// One decimal ID per line
+    Text,
+    /// LEB128 varints, the first ID as is and every following one as the difference to the one before it
+    Varint,
+}
+
+impl std::str::FromStr for ExportFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "text" => Ok(ExportFormat::Text),
+            "varint" => Ok(ExportFormat::Varint),
+            _ => Err(format!("Unknown export format: {s:?}")),
+        }
+    }
+}
+
+/// How many IDs were exported, and their sum
+#[derive(Debug, Default, PartialEq)]
+pub struct ExportSummary {
+    pub count: u64,
+    pub sum: u128,
+}
+
+/// All IDs in the set matching `rule`, in ascending order of their value
+///
+/// The ranges of the set are sorted by value and don't share any IDs, whatever their radix, so this
+/// just walks them in order.
+pub fn invalid_ids<'a>(
+    range_set: &'a RangeSet,
+    rule: &'a dyn Rule,
+) -> impl Iterator<Item = Id> + 'a {
+    range_set.ids().filter(move |id| rule.matches(id))
+}
+
+/// Write each ID to `writer` as soon as it's produced, keeping a running count and sum
+pub fn export(
+    ids: impl Iterator<Item = Id>,
+    writer: &mut impl Write,
+    format: ExportFormat,
+) -> io::Result<ExportSummary> {
+    let mut summary = ExportSummary::default();
+    let mut previous = 0;
+    for id in ids {
+        match format {
+            ExportFormat::Text => writeln!(writer, "{}", id.value)?,
+            ExportFormat::Varint => write_varint(writer, id.value - previous)?,
+        }
+        previous = id.value;
+        summary.count += 1;
+        summary.sum += id.value;
+    }
+    writer.flush()?;
+    Ok(summary)
+}
+
+fn write_varint(writer: &mut impl Write, mut value: u128) -> io::Result<()> {
+    // a u128 takes at most 19 groups of 7 bits
+    let mut buffer = [0u8; 19];
+    let mut length = 0;
+    loop {
+        let byte = (value & 0x7f) as u8;
+        value >>= 7;
+        if value == 0 {
+            buffer[length] = byte;
+            length += 1;
+            break;
+        }
+        buffer[length] = byte | 0x80;
+        length += 1;
+    }
+    writer.write_all(&buffer[..length])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::IdRange;
+    use crate::parse_ranges;
+    use crate::rules::Repeated;
+
+    /// Undo the varint export, back to the original IDs
+    fn read_varints(mut bytes: &[u8]) -> Vec<u128> {
+        let mut values = Vec::new();
+        let mut previous = 0;
+        while !bytes.is_empty() {
+            let mut delta = 0u128;
+            let mut shift = 0;
+            loop {
+                let (&byte, rest) = bytes.split_first().expect("Truncated varint");
+                bytes = rest;
+                delta |= u128::from(byte & 0x7f) << shift;
+                shift += 7;
+                if byte & 0x80 == 0 {
+                    break;
+                }
+            }
+            previous += delta;
+            values.push(previous);
+        }
+        values
+    }
+
+    fn example_set() -> RangeSet {
+        RangeSet::new(parse_ranges("95-115,11-15,998-1012,0x10-0x30").unwrap())
+    }
+
+    #[test]
+    fn test_ascending_across_radixes() {
+        let values: Vec<u128> = invalid_ids(&example_set(), &Repeated)
+            .map(|id| id.value)
+            .collect();
+        // the hex IDs 0x11 and 0x22 fall between the decimal ones
+        assert_eq!(values, vec![11, 17, 34, 99, 111, 999, 1010]);
+    }
+
+    #[test]
+    fn test_text() {
+        let mut output = Vec::new();
+        let summary = export(
+            invalid_ids(&example_set(), &Repeated),
+            &mut output,
+            ExportFormat::Text,
+        )
+        .unwrap();
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "11\n17\n34\n99\n111\n999\n1010\n"
+        );
+        assert_eq!(
+            summary,
+            ExportSummary {
+                count: 7,
+                sum: 11 + 17 + 34 + 99 + 111 + 999 + 1010
+            }
+        );
+    }
+
+    #[test]
+    fn test_varint_round_trip() {
+        let mut output = Vec::new();
+        let summary = export(
+            invalid_ids(&example_set(), &Repeated),
+            &mut output,
+            ExportFormat::Varint,
+        )
+        .unwrap();
+        // every gap fits in a single byte, except the 888 between 111 and 999
+        assert_eq!(output.len(), 8);
+        assert_eq!(read_varints(&output), vec![11, 17, 34, 99, 111, 999, 1010]);
+        assert_eq!(summary.count, 7);
+
+        let largest = Id::new(u128::MAX);
+        let mut output = Vec::new();
+        export(
+            [Id::new(0), largest].into_iter(),
+            &mut output,
+            ExportFormat::Varint,
+        )
+        .unwrap();
+        assert_eq!(output.len(), 1 + 19);
+        assert_eq!(read_varints(&output), vec![0, u128::MAX]);
+    }
+
+    #[test]
+    fn test_matches_scan() {
+        let range_set = RangeSet::new(parse_ranges(include_str!("../data/input.txt")).unwrap());
+        let mut output = Vec::new();
+        let summary = export(
+            invalid_ids(&range_set, &Repeated),
+            &mut output,
+            ExportFormat::Varint,
+        )
+        .unwrap();
+        assert_eq!(summary.sum, 33832678380);
+
+        let scanned = crate::parallel::fold(
+            &range_set,
+            4,
+            &Repeated,
+            Vec::new,
+            |scanned, id| scanned.push(id.value),
+            Vec::extend,
+        );
+        assert_eq!(read_varints(&output), scanned);
+    }
+
+    #[test]
+    fn test_empty() {
+        let range_set = RangeSet::new(Vec::<IdRange>::new());
+        let mut output = Vec::new();
+        let summary = export(
+            invalid_ids(&range_set, &Repeated),
+            &mut output,
+            ExportFormat::Varint,
+        )
+        .unwrap();
+        assert!(output.is_empty());
+        assert_eq!(summary, ExportSummary::default());
+    }
+}
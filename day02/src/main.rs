@@ -1,57 +1,571 @@
+mod cli;
+mod counting;
+mod export;
+mod parallel;
+mod range_set;
+mod report;
+mod rules;
+mod serve;
+mod suggest;
+
+use cli::Command;
+use range_set::RangeSet;
+use rules::Rule;
+use std::collections::BTreeMap;
+
+/// Most digits an ID can have, which is in base 2
+const MAX_DIGITS: usize = 128;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
 struct Id {
-    value: i64,
+    value: u128,
+    radix: u32,
 }
 
 impl Id {
-    fn new(value: i64) -> Self {
-        Id { value }
+    fn new(value: u128) -> Self {
+        Id { value, radix: 10 }
+    }
+
+    /// Check the ID's digits in another base, from 2 up to 36
+    fn in_radix(self, radix: u32) -> Self {
+        assert!((2..=36).contains(&radix), "Invalid radix: {radix}");
+        Id { radix, ..self }
+    }
+
+    /// Write the digits of the ID in its radix into `buffer`, returning them most significant first
+    fn digits<'a>(&self, buffer: &'a mut [u8; MAX_DIGITS]) -> &'a [u8] {
+        let radix = self.radix as u128;
+        let mut value = self.value;
+        let mut start = MAX_DIGITS;
+        loop {
+            start -= 1;
+            buffer[start] = (value % radix) as u8;
+            value /= radix;
+            if value == 0 {
+                break;
+            }
+        }
+        &buffer[start..]
     }
 
     /// A invalid ID is a sequence of digits repeated at least twice
     fn is_valid(&self) -> bool {
-        let string_representation = self.value.to_string();
-        if string_representation.len() == 1 {
-            return true;
+        let mut buffer = [0; MAX_DIGITS];
+        self.repeated_period(&mut buffer).is_none()
+    }
+
+    /// Describe how an invalid ID repeats, or `None` if it's valid
+    fn analyze(&self) -> Option<Repetition> {
+        let mut buffer = [0; MAX_DIGITS];
+        let period = self.repeated_period(&mut buffer)?;
+        let digits = self.digits(&mut buffer);
+        let unit = digits[..period]
+            .iter()
+            .map(|&digit| char::from_digit(digit as u32, self.radix).unwrap())
+            .collect();
+        Some(Repetition {
+            unit,
+            period,
+            repetitions: digits.len() / period,
+        })
+    }
+
+    /// The closest valid IDs below and above this one
+    ///
+    /// Instead of checking one ID at a time, the IDs next to this one are counted with the closed form
+    /// in ever longer stretches, so a run of invalid IDs is jumped over in a logarithmic number of counts.
+    fn nearest_valid(&self) -> NearestValid {
+        let all_invalid = |low: u128, high: u128| {
+            counting::count_invalid_wide(low, high, self.radix) == high - low + 1
+        };
+        let value = self.value;
+        let run_below = invalid_run(value, |length| all_invalid(value - length, value - 1));
+        let room_above = u128::MAX - value;
+        let run_above = invalid_run(room_above, |length| all_invalid(value + 1, value + length));
+        NearestValid {
+            below: (run_below < value).then(|| Id {
+                value: value - run_below - 1,
+                ..*self
+            }),
+            above: (run_above < room_above).then(|| Id {
+                value: value + run_above + 1,
+                ..*self
+            }),
         }
+    }
 
-        // use the length of the sequence to gather all possible sequences
-        let max_sequence_length = string_representation.len() / 2;
-        let chars: Vec<char> = string_representation.chars().collect();
-        for length in 1..=max_sequence_length {
-            // split the string up in sequences of `length`
-            let chunks: Vec<&[char]> = chars.chunks(length).collect();
-            assert!(chunks.len() >= 2);
-            // if all chunks are the same, it's invalid
-            if chunks.iter().skip(1).all(|chunk| chunk == &chunks[0]) {
-                return false;
+    /// Length of the shortest sequence the ID's digits are made of, if it's repeated at least twice
+    fn repeated_period(&self, buffer: &mut [u8; MAX_DIGITS]) -> Option<usize> {
+        let digits = self.digits(buffer);
+        let len = digits.len();
+
+        // a sequence can only be repeated if it fits a whole number of times
+        // the first length that matches is the shortest, so any longer one is made of it too
+        (1..=len / 2)
+            .filter(|&length| len.is_multiple_of(length))
+            // if every digit matches the one a sequence earlier, all sequences are the same
+            .find(|&length| (length..len).all(|i| digits[i] == digits[i - length]))
+    }
+}
+
+impl std::fmt::Display for Id {
+    /// Write the ID in its radix, in the same notation range bounds are parsed from
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self.radix {
+            10 => {}
+            16 => write!(f, "0x")?,
+            8 => write!(f, "0o")?,
+            2 => write!(f, "0b")?,
+            radix => write!(f, "{radix}#")?,
+        }
+        let mut buffer = [0; MAX_DIGITS];
+        for &digit in self.digits(&mut buffer) {
+            write!(f, "{}", char::from_digit(digit as u32, self.radix).unwrap())?;
+        }
+        Ok(())
+    }
+}
+
+/// Length of the run of invalid IDs next to an ID on one side, where there are `room` IDs
+///
+/// `all_invalid(length)` tells whether the first `length` IDs on that side are all invalid. The length
+/// is doubled until they aren't, then the end of the run is found between the last two lengths.
+fn invalid_run(room: u128, all_invalid: impl Fn(u128) -> bool) -> u128 {
+    let mut invalid: u128 = 0;
+    let mut mixed = loop {
+        let length = invalid.saturating_mul(2).max(1).min(room);
+        if length == invalid {
+            return invalid;
+        }
+        if !all_invalid(length) {
+            break length;
+        }
+        invalid = length;
+    };
+    while mixed - invalid > 1 {
+        let middle = invalid + (mixed - invalid) / 2;
+        if all_invalid(middle) {
+            invalid = middle;
+        } else {
+            mixed = middle;
+        }
+    }
+    invalid
+}
+
+/// The closest valid IDs on either side of an ID, if there are any
+#[derive(Debug, PartialEq)]
+struct NearestValid {
+    below: Option<Id>,
+    above: Option<Id>,
+}
+
+/// The shortest sequence an invalid ID is made of, e.g. 2121212121 is "21" repeated 5 times
+#[derive(Debug, PartialEq)]
+struct Repetition {
+    unit: String,
+    period: usize,
+    repetitions: usize,
+}
+
+impl std::fmt::Display for Repetition {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{:?} x {}", self.unit, self.repetitions)
+    }
+}
+
+/// Invalid IDs shown for every period in a scan summary
+const EXAMPLES_PER_PERIOD: usize = 3;
+
+/// Count and sum of the invalid IDs sharing a period, with the first few of them as examples
+#[derive(Debug, Default, PartialEq)]
+struct PeriodSummary {
+    count: u64,
+    sum: u128,
+    examples: Vec<Id>,
+}
+
+/// Invalid IDs counted by the length of their shortest repeated sequence, without keeping all of them
+///
+/// IDs that custom rules reject without repeating a sequence are counted under `None`.
+#[derive(Debug, Default, PartialEq)]
+struct ScanSummary {
+    periods: BTreeMap<Option<usize>, PeriodSummary>,
+}
+
+impl ScanSummary {
+    fn add(&mut self, id: Id) {
+        let mut buffer = [0; MAX_DIGITS];
+        let summary = self
+            .periods
+            .entry(id.repeated_period(&mut buffer))
+            .or_default();
+        summary.count += 1;
+        summary.sum += id.value;
+        if summary.examples.len() < EXAMPLES_PER_PERIOD {
+            summary.examples.push(id);
+        }
+    }
+
+    /// Add the IDs counted in `other`, which all come after the ones counted so far
+    fn merge(&mut self, other: ScanSummary) {
+        for (period, other) in other.periods {
+            let summary = self.periods.entry(period).or_default();
+            summary.count += other.count;
+            summary.sum += other.sum;
+            let room = EXAMPLES_PER_PERIOD - summary.examples.len();
+            summary
+                .examples
+                .extend(other.examples.into_iter().take(room));
+        }
+    }
+
+    fn count(&self) -> u64 {
+        self.periods.values().map(|summary| summary.count).sum()
+    }
+
+    fn sum(&self) -> u128 {
+        self.periods.values().map(|summary| summary.sum).sum()
+    }
+}
+
+/// An inclusive range of IDs, written in `radix`
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct IdRange {
+    start: u128,
+    end: u128,
+    radix: u32,
+}
+
+impl IdRange {
+    fn ids(&self) -> impl Iterator<Item = Id> + '_ {
+        (self.start..=self.end).map(|value| Id::new(value).in_radix(self.radix))
+    }
+}
+
+impl std::fmt::Display for IdRange {
+    /// Write the bounds in the range's radix, the way they're written in the input
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(
+            f,
+            "{}-{}",
+            Id::new(self.start).in_radix(self.radix),
+            Id::new(self.end).in_radix(self.radix)
+        )
+    }
+}
+
+/// A position in the input, counted from 1
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Location {
+    line: usize,
+    column: usize,
+}
+
+impl Location {
+    /// Location of the byte at `offset` in `input`
+    fn of(input: &str, offset: usize) -> Self {
+        let before = &input[..offset];
+        let line_start = before.rfind('\n').map_or(0, |newline| newline + 1);
+        Location {
+            line: before.matches('\n').count() + 1,
+            column: before[line_start..].chars().count() + 1,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+struct ParseError {
+    location: Location,
+    kind: ParseErrorKind,
+}
+
+#[derive(Debug, PartialEq)]
+enum ParseErrorKind {
+    EmptyEntry,
+    MissingSeparator(String),
+    ExtraSeparator(String),
+    MissingBound(String),
+    InvalidBound(String),
+    SignedBound(String),
+    InvalidRadix(String),
+    MixedRadix(String),
+    Reversed(String),
+}
+
+impl std::fmt::Display for ParseErrorKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            ParseErrorKind::EmptyEntry => write!(f, "empty range between commas"),
+            ParseErrorKind::MissingSeparator(range) => write!(f, "range {range:?} has no '-'"),
+            ParseErrorKind::ExtraSeparator(range) => {
+                write!(f, "range {range:?} has more than one '-'")
+            }
+            ParseErrorKind::MissingBound(range) => {
+                write!(f, "range {range:?} is missing a bound")
+            }
+            ParseErrorKind::InvalidBound(bound) => write!(f, "invalid range bound: {bound:?}"),
+            ParseErrorKind::SignedBound(bound) => {
+                write!(
+                    f,
+                    "range bound {bound:?} can't have a sign, IDs are never negative"
+                )
+            }
+            ParseErrorKind::InvalidRadix(bound) => {
+                write!(f, "radix of {bound:?} must be between 2 and 36")
+            }
+            ParseErrorKind::MixedRadix(range) => {
+                write!(f, "bounds of range {range:?} use different radixes")
+            }
+            ParseErrorKind::Reversed(range) => {
+                write!(f, "range {range:?} starts after it ends")
             }
         }
-        true
     }
 }
 
+impl std::fmt::Display for ParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(
+            f,
+            "line {}, column {}: {}",
+            self.location.line, self.location.column, self.kind
+        )
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+/// Parse comma separated ranges like `11-22,95-115`
+///
+/// Bounds are decimal unless prefixed with `0x`, `0o` or `0b`, or tagged with their radix like `36#zz`.
+/// Whitespace and newlines around ranges and bounds are ignored, anything else that isn't exactly
+/// `<start>-<end>` with `start <= end` is an error pointing at the offending range or bound.
+fn parse_ranges(input: &str) -> Result<Vec<IdRange>, ParseError> {
+    if input.trim().is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let mut ranges = Vec::new();
+    let mut entry_offset = 0;
+    for entry in input.split(',') {
+        // offset of the first non-whitespace character, so errors point at the range itself
+        let offset = entry_offset + (entry.len() - entry.trim_start().len());
+        entry_offset += entry.len() + 1;
+        let error = |offset, kind| ParseError {
+            location: Location::of(input, offset),
+            kind,
+        };
+
+        let range = entry.trim();
+        if range.is_empty() {
+            return Err(error(offset, ParseErrorKind::EmptyEntry));
+        }
+        let (start, end) = match range.split('-').collect::<Vec<_>>()[..] {
+            [start, end] => (start, end),
+            [_] => {
+                let kind = ParseErrorKind::MissingSeparator(range.to_string());
+                return Err(error(offset, kind));
+            }
+            _ => {
+                let kind = ParseErrorKind::ExtraSeparator(range.to_string());
+                return Err(error(offset, kind));
+            }
+        };
+        if start.trim().is_empty() || end.trim().is_empty() {
+            let kind = ParseErrorKind::MissingBound(range.to_string());
+            return Err(error(offset, kind));
+        }
+
+        let end_offset = offset + start.len() + 1 + (end.len() - end.trim_start().len());
+        let start_offset = offset;
+        let (start_radix, start) =
+            parse_bound(start.trim()).map_err(|kind| error(start_offset, kind))?;
+        let (end_radix, end) = parse_bound(end.trim()).map_err(|kind| error(end_offset, kind))?;
+        if start_radix != end_radix {
+            let kind = ParseErrorKind::MixedRadix(range.to_string());
+            return Err(error(offset, kind));
+        }
+        if start > end {
+            let kind = ParseErrorKind::Reversed(range.to_string());
+            return Err(error(offset, kind));
+        }
+        ranges.push(IdRange {
+            start,
+            end,
+            radix: start_radix,
+        });
+    }
+    Ok(ranges)
+}
+
+/// Parse a single range bound, returning its radix and value
+fn parse_bound(bound: &str) -> Result<(u32, u128), ParseErrorKind> {
+    let (radix, digits) = if let Some((radix, digits)) = bound.split_once('#') {
+        let radix = radix
+            .parse::<u32>()
+            .ok()
+            .filter(|radix| (2..=36).contains(radix))
+            .ok_or_else(|| ParseErrorKind::InvalidRadix(bound.to_string()))?;
+        (radix, digits)
+    } else if let Some(digits) = bound.strip_prefix("0x") {
+        (16, digits)
+    } else if let Some(digits) = bound.strip_prefix("0o") {
+        (8, digits)
+    } else if let Some(digits) = bound.strip_prefix("0b") {
+        (2, digits)
+    } else {
+        (10, bound)
+    };
+    // `from_str_radix` would happily skip over a `+`
+    if digits.starts_with(['+', '-']) {
+        return Err(ParseErrorKind::SignedBound(bound.to_string()));
+    }
+    let value = u128::from_str_radix(digits, radix)
+        .map_err(|_| ParseErrorKind::InvalidBound(bound.to_string()))?;
+    Ok((radix, value))
+}
+
 fn main() {
-    let id_ranges = include_str!("../data/input.txt")
-        .trim()
-        .split(',')
-        .map(|range| {
-            let mut bounds = range.split('-').map(|s| s.parse::<i64>().unwrap());
-            let start = bounds.next().unwrap();
-            let end = bounds.next().unwrap();
-            (start, end)
-        })
-        .collect::<Vec<(i64, i64)>>();
+    let command = Command::parse(std::env::args().skip(1)).unwrap_or_else(|e| {
+        eprintln!("{e}\n{}", cli::USAGE);
+        std::process::exit(2);
+    });
+
+    match command {
+        Command::Scan(options) => {
+            let rules = load_rules(options.rules.as_deref());
+            scan(options.threads, rules.as_deref())
+        }
+        Command::Report {
+            scan,
+            format,
+            limit,
+        } => {
+            let rules = load_rules(scan.rules.as_deref());
+            let reports = report::build(
+                &puzzle_ranges(),
+                scan.threads,
+                rules.as_deref().unwrap_or(&rules::Repeated),
+                limit,
+            );
+            print!("{}", report::render(&reports, format));
+        }
+        Command::Suggest { input } => {
+            let input = match input {
+                Some(path) => std::fs::read_to_string(&path),
+                None => std::io::read_to_string(std::io::stdin()),
+            }
+            .unwrap_or_else(|e| panic!("Failed to read IDs: {e}"));
+            for line in suggest::annotate(&input) {
+                println!("{line}");
+            }
+        }
+        Command::Export {
+            rules,
+            format,
+            output,
+        } => {
+            let rules = load_rules(rules.as_deref());
+            let range_set = RangeSet::new(puzzle_ranges());
+            let ids = export::invalid_ids(&range_set, rules.as_deref().unwrap_or(&rules::Repeated));
+            let summary = match output {
+                Some(path) => {
+                    let file = std::fs::File::create(&path)
+                        .unwrap_or_else(|e| panic!("Failed to create {}: {e}", path.display()));
+                    export::export(ids, &mut std::io::BufWriter::new(file), format)
+                }
+                None => export::export(ids, &mut std::io::stdout().lock(), format),
+            }
+            .unwrap_or_else(|e| panic!("Failed to export invalid IDs: {e}"));
+            // stdout may be the export itself, so the summary goes to stderr
+            eprintln!(
+                "Exported {} invalid IDs, sum {}",
+                summary.count, summary.sum
+            );
+        }
+        Command::Serve(address) => {
+            serve::serve(&address).unwrap_or_else(|e| panic!("Server failed: {e}"));
+        }
+    }
+}
+
+fn puzzle_ranges() -> Vec<IdRange> {
+    parse_ranges(include_str!("../data/input.txt")).unwrap_or_else(|e| panic!("Invalid input: {e}"))
+}
+
+/// Read and parse a rule file, if one was given
+fn load_rules(path: Option<&std::path::Path>) -> Option<Box<dyn Rule>> {
+    path.map(|path| {
+        let rules = std::fs::read_to_string(path)
+            .unwrap_or_else(|e| panic!("Failed to read {}: {e}", path.display()));
+        rules::parse_rules(&rules)
+            .unwrap_or_else(|e| panic!("Invalid rules in {}: {e}", path.display()))
+    })
+}
+
+/// Find the invalid IDs in the puzzle input
+///
+/// Without custom rules, the puzzle's repeated sequence rule is used and checked against the closed form.
+fn scan(threads: usize, rules: Option<&dyn Rule>) {
+    let range_set = RangeSet::new(puzzle_ranges());
+    for overlap in range_set.overlaps() {
+        eprintln!("Warning: {overlap}, its IDs are only counted once");
+    }
+
+    let summary = parallel::summarize(&range_set, threads, rules.unwrap_or(&rules::Repeated));
+    println!("Sum of invalid IDs: {}", summary.sum());
 
-    let ids_to_check = id_ranges
+    println!("Invalid IDs by period:");
+    for (period, group) in &summary.periods {
+        let examples = group
+            .examples
+            .iter()
+            .map(|id| match id.analyze() {
+                Some(repetition) => format!("{} = {repetition}", id.value),
+                None => id.value.to_string(),
+            })
+            .collect::<Vec<_>>()
+            .join(", ");
+        let label = match period {
+            Some(period) => format!("period {period}"),
+            None => "no repeated sequence".to_string(),
+        };
+        println!("  {label}: {} IDs, e.g. {examples}", group.count);
+    }
+
+    println!("Number of invalid IDs: {}", summary.count());
+
+    if rules.is_some() {
+        return;
+    }
+    // the closed form only handles IDs that fit in a u64
+    let closed_form = range_set
+        .ranges()
         .iter()
-        .flat_map(|(start, end)| *start..=*end)
-        .map(Id::new);
-    let invalid_ids: Vec<i64> = ids_to_check
-        .filter(|id| !id.is_valid())
-        .map(|id| id.value)
-        .collect();
-    let invalid_sum: i64 = invalid_ids.iter().sum();
-    println!("Sum of invalid IDs: {invalid_sum}");
+        .map(|range| {
+            let start = u64::try_from(range.start).ok()?;
+            let end = u64::try_from(range.end).ok()?;
+            Some((
+                counting::count_invalid(start, end, range.radix),
+                counting::sum_invalid(start, end, range.radix),
+            ))
+        })
+        .try_fold((0, 0), |(count, sum), counted| {
+            counted.map(|(range_count, range_sum)| (count + range_count, sum + range_sum))
+        });
+    // a mismatch is a bug in one of the two, but the scan's result still stands
+    if let Some((closed_form_count, closed_form_sum)) = closed_form
+        && (closed_form_count, closed_form_sum) != (summary.count(), summary.sum())
+    {
+        eprintln!(
+            "Warning: the closed form gives {closed_form_count} invalid IDs summing to {closed_form_sum}, \
+             not the scanned {} summing to {}",
+            summary.count(),
+            summary.sum()
+        );
+    }
 }
 
 #[cfg(test)]
@@ -97,19 +611,19 @@ mod tests {
             2121212121,
         ];
 
-        let expected_sum = expected_invalid_ids.iter().sum::<i64>();
+        let expected_sum = expected_invalid_ids.iter().sum::<u128>();
 
         let ids_to_check = example_ranges
             .iter()
             .flat_map(|(start, end)| *start..=*end)
             .map(Id::new);
         println!("Total IDs to check: {}", ids_to_check.clone().count());
-        let invalid_ids: Vec<i64> = ids_to_check
+        let invalid_ids: Vec<u128> = ids_to_check
             .filter(|id| !id.is_valid())
             .map(|id| id.value)
             .collect();
         println!("Invalid IDs: {:?}", invalid_ids);
-        let invalid_sum: i64 = invalid_ids.iter().sum();
+        let invalid_sum: u128 = invalid_ids.iter().sum();
 
         assert_eq!(invalid_ids, expected_invalid_ids);
         assert_eq!(invalid_sum, expected_sum);
@@ -117,26 +631,486 @@ mod tests {
 
     #[test]
     fn test_full_input() {
-        let id_ranges = include_str!("../data/input.txt")
-            .trim()
-            .split(',')
-            .map(|range| {
-                let mut bounds = range.split('-').map(|s| s.parse::<i64>().unwrap());
-                let start = bounds.next().unwrap();
-                let end = bounds.next().unwrap();
-                (start, end)
-            })
-            .collect::<Vec<(i64, i64)>>();
+        let range_set = RangeSet::new(parse_ranges(include_str!("../data/input.txt")).unwrap());
 
-        let ids_to_check = id_ranges
-            .iter()
-            .flat_map(|(start, end)| *start..=*end)
-            .map(Id::new);
-        let invalid_ids: Vec<i64> = ids_to_check
+        let ids_to_check = range_set.ids();
+        let invalid_ids: Vec<u128> = ids_to_check
             .filter(|id| !id.is_valid())
             .map(|id| id.value)
             .collect();
-        let invalid_sum: i64 = invalid_ids.iter().sum();
+        let invalid_sum: u128 = invalid_ids.iter().sum();
         assert_eq!(invalid_sum, 33832678380);
     }
+
+    /// Xorshift generator for the random range lists, seeded per test so a failing input can be replayed
+    struct Rng(u64);
+
+    impl Rng {
+        fn next_u64(&mut self) -> u64 {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 7;
+            self.0 ^= self.0 << 17;
+            self.0
+        }
+
+        /// Random index in [0, len)
+        fn below(&mut self, len: usize) -> usize {
+            (self.next_u64() % len as u64) as usize
+        }
+    }
+
+    /// Line, column and kind of the error parsing `input`
+    fn parse_error(input: &str) -> (usize, usize, ParseErrorKind) {
+        let error = parse_ranges(input).unwrap_err();
+        (error.location.line, error.location.column, error.kind)
+    }
+
+    #[test]
+    fn test_parse_ranges_errors() {
+        assert_eq!(
+            parse_ranges("11-22,95-115"),
+            Ok(vec![
+                IdRange {
+                    start: 11,
+                    end: 22,
+                    radix: 10
+                },
+                IdRange {
+                    start: 95,
+                    end: 115,
+                    radix: 10
+                }
+            ])
+        );
+        assert_eq!(
+            parse_error("11-22,95"),
+            (1, 7, ParseErrorKind::MissingSeparator("95".to_string()))
+        );
+        assert_eq!(
+            parse_error("+5-10"),
+            (1, 1, ParseErrorKind::SignedBound("+5".to_string()))
+        );
+        assert_eq!(
+            parse_error("0x5-0x+10"),
+            (1, 5, ParseErrorKind::SignedBound("0x+10".to_string()))
+        );
+        assert_eq!(
+            parse_error("1-340282366920938463463374607431768211456"),
+            (
+                1,
+                3,
+                ParseErrorKind::InvalidBound("340282366920938463463374607431768211456".to_string())
+            )
+        );
+        assert_eq!(
+            parse_error("1 1-22"),
+            (1, 1, ParseErrorKind::InvalidBound("1 1".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_parse_ranges_missing_bounds() {
+        assert_eq!(
+            parse_error("-11"),
+            (1, 1, ParseErrorKind::MissingBound("-11".to_string()))
+        );
+        assert_eq!(
+            parse_error("11-22,33- "),
+            (1, 7, ParseErrorKind::MissingBound("33-".to_string()))
+        );
+        assert_eq!(
+            parse_error("-"),
+            (1, 1, ParseErrorKind::MissingBound("-".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_parse_ranges_extra_dashes() {
+        assert_eq!(
+            parse_error("11-22-33"),
+            (1, 1, ParseErrorKind::ExtraSeparator("11-22-33".to_string()))
+        );
+        // a negative bound is just another dash
+        assert_eq!(
+            parse_error("1-2,5--3"),
+            (1, 5, ParseErrorKind::ExtraSeparator("5--3".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_parse_ranges_reversed() {
+        assert_eq!(
+            parse_error("11-22,\n 115-95"),
+            (2, 2, ParseErrorKind::Reversed("115-95".to_string()))
+        );
+        assert_eq!(
+            parse_error("0x20-0x1f"),
+            (1, 1, ParseErrorKind::Reversed("0x20-0x1f".to_string()))
+        );
+        // a range of one ID is fine
+        assert_eq!(parse_ranges("7-7").unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_parse_ranges_whitespace() {
+        // whitespace and newlines around ranges and bounds are normalized away
+        let ranges = parse_ranges(" 11-22,\n95 - 115 ,\r\n\t998-1012\n").unwrap();
+        let as_tuples: Vec<(u128, u128)> = ranges
+            .iter()
+            .map(|range| (range.start, range.end))
+            .collect();
+        assert_eq!(as_tuples, vec![(11, 22), (95, 115), (998, 1012)]);
+        assert_eq!(parse_ranges(" \n"), Ok(vec![]));
+
+        // but an empty range between two commas, or a trailing comma, isn't
+        assert_eq!(
+            parse_error("11-22,,95-115"),
+            (1, 7, ParseErrorKind::EmptyEntry)
+        );
+        assert_eq!(
+            parse_error("11-22,\n95-115,\n"),
+            (3, 1, ParseErrorKind::EmptyEntry)
+        );
+    }
+
+    #[test]
+    fn test_parse_error_display() {
+        let error = parse_ranges("11-22,\n  95-").unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "line 2, column 3: range \"95-\" is missing a bound"
+        );
+    }
+
+    #[test]
+    fn test_fuzz_parse_ranges() {
+        // digits, dashes and commas make up most of every list, the rest hits radix prefixes, signs,
+        // comments and characters outside any radix
+        const ALPHABET: &[char] = &[
+            '0', '1', '2', '9', '-', ',', '+', ' ', '\n', 'x', 'b', 'z', '#', 'é',
+        ];
+        let mut rng = Rng(0x0011_0022_0095_0115);
+        for _ in 0..20_000 {
+            let length = rng.below(40);
+            let input: String = (0..length)
+                .map(|_| ALPHABET[rng.below(ALPHABET.len())])
+                .collect();
+            // the parser must never panic, and the bounds it accepts must be safe to check
+            if let Ok(ranges) = parse_ranges(&input) {
+                for range in ranges {
+                    Id::new(range.start).in_radix(range.radix).is_valid();
+                    Id::new(range.end).in_radix(range.radix).is_valid();
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_radix_id_validity() {
+        // 0xABAB
+        assert!(!Id::new(0xabab).in_radix(16).is_valid());
+        assert!(Id::new(0xabab).is_valid());
+        // 0b101010 is "10" three times
+        assert!(!Id::new(0b101010).in_radix(2).is_valid());
+        assert!(Id::new(0b101011).in_radix(2).is_valid());
+        // "zz" in base 36
+        assert!(!Id::new(36 * 35 + 35).in_radix(36).is_valid());
+        assert!(Id::new(35).in_radix(36).is_valid());
+        assert!(Id::new(0).in_radix(2).is_valid());
+        // 1111 in decimal is 0x457, which is valid in hex
+        assert!(!Id::new(1111).is_valid());
+        assert!(Id::new(1111).in_radix(16).is_valid());
+    }
+
+    #[test]
+    fn test_parse_radix_ranges() {
+        let ranges = parse_ranges("0x10-0x1f,0o7-0o70,0b1-0b11,36#a-36#zz,11-22").unwrap();
+        let as_tuples: Vec<(u128, u128, u32)> = ranges
+            .iter()
+            .map(|range| (range.start, range.end, range.radix))
+            .collect();
+        assert_eq!(
+            as_tuples,
+            vec![
+                (16, 31, 16),
+                (7, 56, 8),
+                (1, 3, 2),
+                (10, 36 * 35 + 35, 36),
+                (11, 22, 10)
+            ]
+        );
+
+        assert_eq!(
+            parse_error("0x10-20"),
+            (1, 1, ParseErrorKind::MixedRadix("0x10-20".to_string()))
+        );
+        assert_eq!(
+            parse_error("37#1-37#2"),
+            (1, 1, ParseErrorKind::InvalidRadix("37#1".to_string()))
+        );
+        assert_eq!(
+            parse_error("0xg-0xh"),
+            (1, 1, ParseErrorKind::InvalidBound("0xg".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_hex_range_invalid_ids() {
+        let range_set = RangeSet::new(parse_ranges("0x10-0x120").unwrap());
+        let invalid_ids: Vec<u128> = range_set
+            .ids()
+            .filter(|id| !id.is_valid())
+            .map(|id| id.value)
+            .collect();
+        assert_eq!(
+            invalid_ids,
+            vec![
+                0x11, 0x22, 0x33, 0x44, 0x55, 0x66, 0x77, 0x88, 0x99, 0xaa, 0xbb, 0xcc, 0xdd, 0xee,
+                0xff, 0x111
+            ]
+        );
+    }
+
+    /// The original string based `is_valid`, to check and benchmark the allocation-free one against
+    fn is_valid_with_strings(value: u128) -> bool {
+        let string_representation = value.to_string();
+        if string_representation.len() == 1 {
+            return true;
+        }
+        let max_sequence_length = string_representation.len() / 2;
+        let chars: Vec<char> = string_representation.chars().collect();
+        for length in 1..=max_sequence_length {
+            let chunks: Vec<&[char]> = chars.chunks(length).collect();
+            if chunks.iter().skip(1).all(|chunk| chunk == &chunks[0]) {
+                return false;
+            }
+        }
+        true
+    }
+
+    #[test]
+    fn test_is_valid_matches_string_version() {
+        let values = (0..300_000)
+            .chain(1188511880..=1188511890)
+            .chain(2121212118..=2121212124)
+            .chain([
+                u128::MAX,
+                u64::MAX as u128,
+                1111111111111111111,
+                9090909090909090,
+            ]);
+        for value in values {
+            assert_eq!(
+                Id::new(value).is_valid(),
+                is_valid_with_strings(value),
+                "{value}"
+            );
+        }
+    }
+
+    #[test]
+    #[ignore = "benchmark, run with `cargo test --release -- --ignored --nocapture`"]
+    fn bench_is_valid() {
+        use std::hint::black_box;
+        use std::time::Instant;
+
+        let range_set = RangeSet::new(parse_ranges(include_str!("../data/input.txt")).unwrap());
+        let values = || {
+            range_set
+                .ranges()
+                .iter()
+                .flat_map(|range| range.start..=range.end)
+        };
+
+        let start = Instant::now();
+        let string_count = values()
+            .filter(|&value| !is_valid_with_strings(black_box(value)))
+            .count();
+        let string_time = start.elapsed();
+
+        let start = Instant::now();
+        let digit_count = values()
+            .filter(|&value| !Id::new(black_box(value)).is_valid())
+            .count();
+        let digit_time = start.elapsed();
+
+        assert_eq!(string_count, digit_count);
+        println!(
+            "{} IDs: strings {string_time:?}, digits {digit_time:?} ({:.1}x faster)",
+            values().count(),
+            string_time.as_secs_f64() / digit_time.as_secs_f64()
+        );
+    }
+
+    #[test]
+    fn test_analyze() {
+        let repetition = |unit: &str, repetitions| {
+            Some(Repetition {
+                unit: unit.to_string(),
+                period: unit.len(),
+                repetitions,
+            })
+        };
+        assert_eq!(Id::new(2121212121).analyze(), repetition("21", 5));
+        assert_eq!(Id::new(1111).analyze(), repetition("1", 4));
+        assert_eq!(Id::new(1212).analyze(), repetition("12", 2));
+        assert_eq!(Id::new(123123123).analyze(), repetition("123", 3));
+        assert_eq!(Id::new(0xabab).in_radix(16).analyze(), repetition("ab", 2));
+        assert_eq!(Id::new(1234).analyze(), None);
+        assert_eq!(Id::new(7).analyze(), None);
+        assert_eq!(
+            Id::new(2121212121).analyze().unwrap().to_string(),
+            "\"21\" x 5"
+        );
+    }
+
+    #[test]
+    fn test_scan_summary() {
+        let range_set = RangeSet::new(parse_ranges("11-22,95-115,998-1012,222220-222224").unwrap());
+        let mut summary = ScanSummary::default();
+        for id in range_set.ids().filter(|id| !id.is_valid()) {
+            summary.add(id);
+        }
+        let periods: Vec<(Option<usize>, u64, u128, Vec<u128>)> = summary
+            .periods
+            .iter()
+            .map(|(&period, group)| {
+                let examples = group.examples.iter().map(|id| id.value).collect();
+                (period, group.count, group.sum, examples)
+            })
+            .collect();
+        // 222222 is "2" x 6 rather than "22" x 3 or "222" x 2
+        assert_eq!(
+            periods,
+            vec![
+                (
+                    Some(1),
+                    6,
+                    11 + 22 + 99 + 111 + 999 + 222222,
+                    vec![11, 22, 99]
+                ),
+                (Some(2), 1, 1010, vec![1010])
+            ]
+        );
+        assert_eq!((summary.count(), summary.sum()), (7, 224474));
+
+        // IDs matched by custom rules don't have to repeat, but they're still counted
+        let mut summary = ScanSummary::default();
+        summary.add(Id::new(121));
+        summary.add(Id::new(55));
+        let counts: Vec<(Option<usize>, u64)> = summary
+            .periods
+            .iter()
+            .map(|(&period, group)| (period, group.count))
+            .collect();
+        assert_eq!(counts, vec![(None, 1), (Some(1), 1)]);
+    }
+
+    #[test]
+    fn test_merge_summaries() {
+        let ids: Vec<Id> = [11, 22, 33, 44, 1212, 55].map(Id::new).to_vec();
+        let mut whole = ScanSummary::default();
+        for &id in &ids {
+            whole.add(id);
+        }
+        for split in 0..=ids.len() {
+            let (mut first, mut second) = (ScanSummary::default(), ScanSummary::default());
+            ids[..split].iter().for_each(|&id| first.add(id));
+            ids[split..].iter().for_each(|&id| second.add(id));
+            first.merge(second);
+            assert_eq!(first, whole, "split at {split}");
+        }
+    }
+
+    #[test]
+    fn test_near_u128_max() {
+        // 39 digits, so it could repeat a sequence of 1, 3 or 13 digits, but none of them do
+        assert!(Id::new(u128::MAX).is_valid());
+        // in binary it's all ones
+        assert_eq!(
+            Id::new(u128::MAX)
+                .in_radix(2)
+                .analyze()
+                .map(|r| r.repetitions),
+            Some(128)
+        );
+        // the largest invalid decimal ID is "3402823669209" repeated 3 times
+        let largest_invalid = 340282366920934028236692093402823669209;
+        assert_eq!(
+            Id::new(largest_invalid).analyze().map(|r| r.unit),
+            Some("3402823669209".to_string())
+        );
+        let range_set =
+            RangeSet::new(parse_ranges(&format!("{largest_invalid}-{}", u128::MAX)).unwrap());
+        assert_eq!(range_set.ranges()[0].end, u128::MAX);
+        let near_max: Vec<u128> =
+            RangeSet::new(parse_ranges(&format!("{}-{}", u128::MAX - 1000, u128::MAX)).unwrap())
+                .ids()
+                .filter(|id| !id.is_valid())
+                .map(|id| id.value)
+                .collect();
+        assert!(near_max.is_empty());
+        assert!(!Id::new(largest_invalid).is_valid());
+        assert!(Id::new(largest_invalid + 1).is_valid());
+    }
+
+    #[test]
+    fn test_nearest_valid() {
+        let nearest = Id::new(446446).nearest_valid();
+        assert_eq!(nearest.below, Some(Id::new(446445)));
+        assert_eq!(nearest.above, Some(Id::new(446447)));
+
+        // 11 and 22 are invalid, but 10 and 12 are fine
+        let nearest = Id::new(11).nearest_valid();
+        assert_eq!(nearest.below, Some(Id::new(10)));
+        assert_eq!(nearest.above, Some(Id::new(12)));
+
+        let nearest = Id::new(0).nearest_valid();
+        assert_eq!(nearest.below, None);
+        assert_eq!(nearest.above, Some(Id::new(1)));
+
+        let nearest = Id::new(u128::MAX).in_radix(2).nearest_valid();
+        assert_eq!(nearest.below, Some(Id::new(u128::MAX - 1).in_radix(2)));
+        assert_eq!(nearest.above, None);
+        let nearest = Id::new(u128::MAX).nearest_valid();
+        assert_eq!(nearest.below, Some(Id::new(u128::MAX - 1)));
+        assert_eq!(nearest.above, None);
+    }
+
+    #[test]
+    fn test_nearest_valid_matches_stepping() {
+        for radix in [2, 3, 10, 36] {
+            for value in (0..3000).chain(u64::MAX as u128 - 100..u64::MAX as u128 + 100) {
+                let id = Id::new(value).in_radix(radix);
+                let is_valid = |value: &u128| Id::new(*value).in_radix(radix).is_valid();
+                let below = (0..value).rev().find(is_valid);
+                let above = (value + 1..).find(is_valid);
+                let nearest = id.nearest_valid();
+                assert_eq!(nearest.below.map(|id| id.value), below, "{id}");
+                assert_eq!(nearest.above.map(|id| id.value), above, "{id}");
+            }
+        }
+    }
+
+    #[test]
+    fn test_invalid_run() {
+        // stands in for a side where the first 1000 IDs are invalid
+        let run = |room| invalid_run(room, |length| length <= 1000);
+        assert_eq!(run(u128::MAX), 1000);
+        assert_eq!(run(1001), 1000);
+        assert_eq!(run(1000), 1000);
+        assert_eq!(run(10), 10);
+        assert_eq!(run(0), 0);
+        assert_eq!(invalid_run(u128::MAX, |_| true), u128::MAX);
+    }
+
+    #[test]
+    fn test_display() {
+        assert_eq!(Id::new(446446).to_string(), "446446");
+        assert_eq!(Id::new(0xabab).in_radix(16).to_string(), "0xabab");
+        assert_eq!(Id::new(0o17).in_radix(8).to_string(), "0o17");
+        assert_eq!(Id::new(5).in_radix(2).to_string(), "0b101");
+        assert_eq!(Id::new(36 * 35).in_radix(36).to_string(), "36#z0");
+        assert_eq!(Id::new(0).to_string(), "0");
+    }
 }
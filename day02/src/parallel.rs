@@ -0,0 +1,216 @@
+use crate::range_set::RangeSet;
+use crate::rules::Rule;
+use crate::{Id, IdRange, ScanSummary};
+
+/// Number of IDs in a range, saturating for the one range that holds every `u128`
+fn width(range: &IdRange) -> u128 {
+    (range.end - range.start).saturating_add(1)
+}
+
+/// Split sorted ranges into at most `chunks` consecutive pieces holding roughly the same number of IDs
+///
+/// Ranges are cut where needed, and the pieces keep the original order, so scanning the chunks one
+/// after the other visits the IDs in the same order as scanning the ranges.
+pub fn partition(ranges: &[IdRange], chunks: usize) -> Vec<Vec<IdRange>> {
+    assert!(chunks > 0, "Can't split ranges into 0 chunks");
+    let total = ranges.iter().map(width).fold(0u128, u128::saturating_add);
+    let chunk_size = total.div_ceil(chunks as u128).max(1);
+
+    let mut partitions = vec![Vec::new()];
+    let mut current_size: u128 = 0;
+    for range in ranges {
+        let mut remaining = *range;
+        loop {
+            let space = chunk_size.saturating_sub(current_size);
+            // the last chunk takes whatever is left over from rounding
+            if partitions.len() == chunks || width(&remaining) <= space {
+                current_size = current_size.saturating_add(width(&remaining));
+                partitions.last_mut().unwrap().push(remaining);
+                break;
+            }
+            // fill up the current chunk and continue with the rest of the range in a new one
+            let split = remaining.start + (space - 1);
+            partitions.last_mut().unwrap().push(IdRange {
+                end: split,
+                ..remaining
+            });
+            remaining.start = split + 1;
+            partitions.push(Vec::new());
+            current_size = 0;
+        }
+        if current_size == chunk_size && partitions.len() < chunks {
+            partitions.push(Vec::new());
+            current_size = 0;
+        }
+    }
+    partitions.retain(|partition| !partition.is_empty());
+    partitions
+}
+
+/// Count and sum the IDs in the set matching `rule` using `threads` threads, keeping only a few of them
+///
+/// Every worker folds its own summary, and they're merged in order, so the examples are the same as a
+/// single threaded scan's.
+pub fn summarize(range_set: &RangeSet, threads: usize, rule: &dyn Rule) -> ScanSummary {
+    fold(
+        range_set,
+        threads,
+        rule,
+        ScanSummary::default,
+        ScanSummary::add,
+        ScanSummary::merge,
+    )
+}
+
+/// Fold the IDs in the set matching `rule` into a summary using `threads` threads
+///
+/// Each worker starts from `empty` and `add`s the IDs of its chunk in order, then the workers'
+/// summaries are `merge`d in the order of their chunks, so the result is the same as a single
+/// threaded fold's as long as merging a later summary is the same as adding its IDs.
+pub fn fold<S: Send>(
+    range_set: &RangeSet,
+    threads: usize,
+    rule: &dyn Rule,
+    empty: impl Fn() -> S + Sync,
+    add: impl Fn(&mut S, Id) + Sync,
+    merge: impl Fn(&mut S, S),
+) -> S {
+    let fold_ranges = |ranges: &[IdRange]| {
+        let mut summary = empty();
+        for id in ranges.iter().flat_map(IdRange::ids) {
+            if rule.matches(&id) {
+                add(&mut summary, id);
+            }
+        }
+        summary
+    };
+    if threads == 1 {
+        return fold_ranges(range_set.ranges());
+    }
+    let partitions = partition(range_set.ranges(), threads);
+    std::thread::scope(|scope| {
+        let handles: Vec<_> = partitions
+            .iter()
+            .map(|partition| scope.spawn(|| fold_ranges(partition)))
+            .collect();
+        // joining in spawn order keeps the result deterministic
+        let mut summary = empty();
+        for handle in handles {
+            merge(&mut summary, handle.join().expect("Worker thread panicked"));
+        }
+        summary
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse_ranges;
+    use crate::rules::Repeated;
+
+    fn range(start: u128, end: u128) -> IdRange {
+        IdRange {
+            start,
+            end,
+            radix: 10,
+        }
+    }
+
+    #[test]
+    fn test_partition_is_balanced() {
+        let ranges = [range(0, 99), range(200, 209), range(300, 389)];
+        let partitions = partition(&ranges, 4);
+        assert_eq!(
+            partitions,
+            vec![
+                vec![range(0, 49)],
+                vec![range(50, 99)],
+                vec![range(200, 209), range(300, 339)],
+                vec![range(340, 389)],
+            ]
+        );
+    }
+
+    #[test]
+    fn test_partition_more_chunks_than_ids() {
+        let ranges = [range(5, 6)];
+        assert_eq!(
+            partition(&ranges, 8),
+            vec![vec![range(5, 5)], vec![range(6, 6)]]
+        );
+        assert!(partition(&[], 3).is_empty());
+    }
+
+    #[test]
+    fn test_partition_huge_range() {
+        let ranges = [range(0, u128::MAX)];
+        let partitions = partition(&ranges, 3);
+        assert_eq!(partitions.len(), 3);
+        assert_eq!(partitions[0][0].start, 0);
+        assert_eq!(partitions[2][0].end, u128::MAX);
+        for pair in partitions.windows(2) {
+            assert_eq!(pair[0][0].end + 1, pair[1][0].start);
+        }
+    }
+
+    #[test]
+    fn test_matches_single_threaded() {
+        let range_set = RangeSet::new(
+            parse_ranges("11-22,95-115,998-1012,1188511880-1188511890,222220-222224").unwrap(),
+        );
+        let expected: Vec<u128> = range_set
+            .ids()
+            .filter(|id| !id.is_valid())
+            .map(|id| id.value)
+            .collect();
+        for threads in [1, 2, 3, 7, 64] {
+            let found = fold(
+                &range_set,
+                threads,
+                &Repeated,
+                Vec::new,
+                |found, id| found.push(id.value),
+                Vec::extend,
+            );
+            assert_eq!(found, expected, "{threads} threads");
+        }
+    }
+
+    #[test]
+    fn test_summarize_matches_single_threaded() {
+        let range_set = RangeSet::new(
+            parse_ranges("11-22,95-115,998-1012,1188511880-1188511890,222220-222224,0x10-0x120")
+                .unwrap(),
+        );
+        let expected = summarize(&range_set, 1, &Repeated);
+        // the IDs the hex range shares with the decimal ones are only counted once
+        assert_eq!(expected.count(), 21);
+        for threads in [2, 3, 7, 64] {
+            assert_eq!(
+                summarize(&range_set, threads, &Repeated),
+                expected,
+                "{threads} threads"
+            );
+        }
+    }
+
+    #[test]
+    #[ignore = "benchmark, run with `cargo test --release -- --ignored --nocapture`"]
+    fn bench_thread_scaling() {
+        use std::time::Instant;
+
+        let range_set = RangeSet::new(parse_ranges(include_str!("../data/input.txt")).unwrap());
+        let mut single_threaded = None;
+        for threads in [1, 2, 4, 8, 16] {
+            let start = Instant::now();
+            let sum = summarize(&range_set, threads, &Repeated).sum();
+            let elapsed = start.elapsed();
+            let baseline = *single_threaded.get_or_insert(elapsed);
+            assert_eq!(sum, 33832678380);
+            println!(
+                "{threads:>2} threads: {elapsed:?} ({:.1}x)",
+                baseline.as_secs_f64() / elapsed.as_secs_f64()
+            );
+        }
+    }
+}
@@ -0,0 +1,199 @@
+use crate::{Id, IdRange};
+
+/// Two input ranges that share at least one ID
+#[derive(Debug, PartialEq)]
+pub struct Overlap {
+    pub first: IdRange,
+    pub second: IdRange,
+}
+
+impl std::fmt::Display for Overlap {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "range {} overlaps {}", self.first, self.second)
+    }
+}
+
+/// Sorted, non-overlapping ranges, so every ID is only checked once
+#[derive(Debug, Default)]
+pub struct RangeSet {
+    ranges: Vec<IdRange>,
+    overlaps: Vec<Overlap>,
+}
+
+impl RangeSet {
+    /// Sort the ranges and merge the ones that overlap or touch, dropping empty ones
+    ///
+    /// Ranges are only merged with ranges in the same radix, as the radix changes which IDs are valid.
+    /// Where ranges in different radixes overlap, the shared IDs are reported like any other overlap
+    /// and checked once, in the radix of the range that starts first.
+    pub fn new(ranges: impl IntoIterator<Item = IdRange>) -> Self {
+        let mut sorted: Vec<IdRange> = ranges
+            .into_iter()
+            .filter(|range| range.start <= range.end)
+            .collect();
+        sorted.sort_by_key(|range| (range.start, range.end, range.radix));
+
+        let mut set = RangeSet::default();
+        // the input range reaching furthest into the ranges built so far, the one any overlap is with
+        let mut furthest: Option<IdRange> = None;
+        for range in sorted {
+            let Some(reaching) =
+                furthest.filter(|reaching| reaching.end.saturating_add(1) >= range.start)
+            else {
+                set.ranges.push(range);
+                furthest = Some(range);
+                continue;
+            };
+            // touching ranges are fine to merge, but overlapping ones were probably a mistake
+            if reaching.end >= range.start {
+                set.overlaps.push(Overlap {
+                    first: reaching,
+                    second: range,
+                });
+            }
+            if range.end <= reaching.end {
+                continue;
+            }
+            // the last range built always ends where the furthest reaching input range does
+            let last = set
+                .ranges
+                .last_mut()
+                .expect("Every input range is in a built range");
+            if last.radix == range.radix {
+                last.end = range.end;
+            } else {
+                set.ranges.push(IdRange {
+                    start: reaching.end + 1,
+                    ..range
+                });
+            }
+            furthest = Some(range);
+        }
+        set
+    }
+
+    pub fn ranges(&self) -> &[IdRange] {
+        &self.ranges
+    }
+
+    /// Overlapping input ranges that were merged together
+    pub fn overlaps(&self) -> &[Overlap] {
+        &self.overlaps
+    }
+
+    /// All IDs in the set, each one exactly once
+    pub fn ids(&self) -> impl Iterator<Item = Id> + '_ {
+        self.ranges.iter().flat_map(IdRange::ids)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn range(start: u128, end: u128) -> IdRange {
+        IdRange {
+            start,
+            end,
+            radix: 10,
+        }
+    }
+
+    #[test]
+    fn test_merge() {
+        let set = RangeSet::new([
+            range(20, 30),
+            range(1, 5),
+            range(6, 10),
+            range(25, 40),
+            range(50, 40),
+            range(22, 24),
+            range(100, 100),
+        ]);
+        assert_eq!(
+            set.ranges(),
+            &[range(1, 10), range(20, 40), range(100, 100)]
+        );
+        // touching ranges and empty ranges aren't overlaps
+        assert_eq!(
+            set.overlaps(),
+            &[
+                Overlap {
+                    first: range(20, 30),
+                    second: range(22, 24)
+                },
+                Overlap {
+                    first: range(20, 30),
+                    second: range(25, 40)
+                }
+            ]
+        );
+    }
+
+    #[test]
+    fn test_overlaps_name_input_ranges() {
+        let set = RangeSet::new([range(20, 30), range(31, 35), range(33, 40)]);
+        assert_eq!(set.ranges(), &[range(20, 40)]);
+        // not the merged 20-35, which isn't in the input
+        assert_eq!(
+            set.overlaps(),
+            &[Overlap {
+                first: range(31, 35),
+                second: range(33, 40)
+            }]
+        );
+    }
+
+    #[test]
+    fn test_duplicates_are_counted_once() {
+        let set = RangeSet::new([range(11, 22), range(11, 22), range(15, 33)]);
+        let invalid_ids: Vec<u128> = set
+            .ids()
+            .filter(|id| !id.is_valid())
+            .map(|id| id.value)
+            .collect();
+        assert_eq!(invalid_ids, vec![11, 22, 33]);
+        assert_eq!(set.overlaps().len(), 2);
+    }
+
+    #[test]
+    fn test_radixes_are_kept_apart() {
+        let hex = |start, end| IdRange {
+            start,
+            end,
+            radix: 16,
+        };
+        let set = RangeSet::new([range(10, 20), hex(21, 30)]);
+        assert_eq!(set.ranges(), &[range(10, 20), hex(21, 30)]);
+        assert!(set.overlaps().is_empty());
+
+        // the shared IDs are checked once, in the radix of the range starting first
+        let set = RangeSet::new([hex(15, 30), range(10, 20), range(25, 40)]);
+        assert_eq!(set.ranges(), &[range(10, 20), hex(21, 30), range(31, 40)]);
+        assert_eq!(
+            set.overlaps(),
+            &[
+                Overlap {
+                    first: range(10, 20),
+                    second: hex(15, 30)
+                },
+                Overlap {
+                    first: hex(15, 30),
+                    second: range(25, 40)
+                }
+            ]
+        );
+        let set = RangeSet::new([hex(10, 40), range(20, 30)]);
+        assert_eq!(set.ranges(), &[hex(10, 40)]);
+        assert_eq!(set.overlaps().len(), 1);
+    }
+
+    #[test]
+    fn test_extreme_bounds() {
+        let set = RangeSet::new([
+            range(u128::MAX - 5, u128::MAX),
+            range(u128::MAX - 1, u128::MAX),
+        ]);
+        assert_eq!(set.ranges(), &[range(u128::MAX - 5, u128::MAX)]);
+    }
+}
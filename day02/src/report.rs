@@ -0,0 +1,312 @@
+use crate::parallel;
+use crate::range_set::RangeSet;
+use crate::rules::Rule;
+use crate::{Id, IdRange};
+use std::fmt::Write;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Format {
+    Text,
+    Csv,
+    Json,
+}
+
+impl std::str::FromStr for Format {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "text" => Ok(Format::Text),
+            "csv" => Ok(Format::Csv),
+            "json" => Ok(Format::Json),
+            _ => Err(format!("Unknown report format: {s:?}")),
+        }
+    }
+}
+
+/// The invalid IDs inside one input range, counted and summed but only listed up to a limit
+#[derive(Debug)]
+pub struct RangeReport {
+    pub range: IdRange,
+    pub invalid_count: u64,
+    pub invalid_sum: u128,
+    /// The first invalid IDs of the range
+    pub listed_ids: Vec<Id>,
+}
+
+impl RangeReport {
+    fn width(&self) -> u128 {
+        // saturates for the one range that holds every u128
+        (self.range.end - self.range.start).saturating_add(1)
+    }
+
+    /// Whether there were more invalid IDs than listed
+    fn truncated(&self) -> bool {
+        self.invalid_count > self.listed_ids.len() as u64
+    }
+}
+
+/// Find the IDs matching `rule` in each range on its own, in input order, listing at most `limit` per range
+///
+/// Unlike a scan over the merged `RangeSet`, IDs in overlapping ranges show up in every range they're in.
+pub fn build(
+    ranges: &[IdRange],
+    threads: usize,
+    rule: &dyn Rule,
+    limit: usize,
+) -> Vec<RangeReport> {
+    ranges
+        .iter()
+        .map(|&range| {
+            let empty = || RangeReport {
+                range,
+                invalid_count: 0,
+                invalid_sum: 0,
+                listed_ids: Vec::new(),
+            };
+            parallel::fold(
+                &RangeSet::new([range]),
+                threads,
+                rule,
+                empty,
+                |report, id| {
+                    report.invalid_count += 1;
+                    report.invalid_sum += id.value;
+                    if report.listed_ids.len() < limit {
+                        report.listed_ids.push(id);
+                    }
+                },
+                |report, other| {
+                    report.invalid_count += other.invalid_count;
+                    report.invalid_sum += other.invalid_sum;
+                    let room = limit - report.listed_ids.len();
+                    report
+                        .listed_ids
+                        .extend(other.listed_ids.into_iter().take(room));
+                },
+            )
+        })
+        .collect()
+}
+
+/// Render the report, listing the IDs each range was built with
+pub fn render(reports: &[RangeReport], format: Format) -> String {
+    match format {
+        Format::Text => render_text(reports),
+        Format::Csv => render_csv(reports),
+        Format::Json => render_json(reports),
+    }
+}
+
+fn render_text(reports: &[RangeReport]) -> String {
+    let header = ["Range", "Width", "Invalid", "Sum", "Invalid IDs"];
+    let mut rows: Vec<[String; 5]> = reports
+        .iter()
+        .map(|report| {
+            let mut ids = report
+                .listed_ids
+                .iter()
+                .map(|id| id.to_string())
+                .collect::<Vec<_>>()
+                .join(", ");
+            if report.truncated() {
+                write!(
+                    ids,
+                    ", ... ({} more)",
+                    report.invalid_count - report.listed_ids.len() as u64
+                )
+                .unwrap();
+            }
+            [
+                report.range.to_string(),
+                report.width().to_string(),
+                report.invalid_count.to_string(),
+                report.invalid_sum.to_string(),
+                ids,
+            ]
+        })
+        .collect();
+    rows.push([
+        "Total".to_string(),
+        reports
+            .iter()
+            .map(RangeReport::width)
+            .fold(0u128, u128::saturating_add)
+            .to_string(),
+        reports
+            .iter()
+            .map(|report| report.invalid_count)
+            .sum::<u64>()
+            .to_string(),
+        reports
+            .iter()
+            .map(|report| report.invalid_sum)
+            .sum::<u128>()
+            .to_string(),
+        String::new(),
+    ]);
+
+    // every column is as wide as its widest cell, except the last one which can be as long as it likes
+    let widths: Vec<usize> = (0..header.len())
+        .map(|column| {
+            rows.iter()
+                .map(|row| row[column].len())
+                .chain([header[column].len()])
+                .max()
+                .unwrap()
+        })
+        .collect();
+    let mut output = String::new();
+    let mut write_row = |cells: [&str; 5]| {
+        let line = format!(
+            "{:<w0$}  {:>w1$}  {:>w2$}  {:>w3$}  {}",
+            cells[0],
+            cells[1],
+            cells[2],
+            cells[3],
+            cells[4],
+            w0 = widths[0],
+            w1 = widths[1],
+            w2 = widths[2],
+            w3 = widths[3],
+        );
+        writeln!(output, "{}", line.trim_end()).unwrap();
+    };
+    write_row(header);
+    for row in &rows {
+        write_row(row.each_ref().map(String::as_str));
+    }
+    output
+}
+
+fn render_csv(reports: &[RangeReport]) -> String {
+    let mut output =
+        "start,end,radix,width,invalid_count,invalid_sum,invalid_ids,truncated\n".to_string();
+    for report in reports {
+        // IDs are separated by spaces so the field never needs quoting
+        let ids = report
+            .listed_ids
+            .iter()
+            .map(|id| id.value.to_string())
+            .collect::<Vec<_>>()
+            .join(" ");
+        writeln!(
+            output,
+            "{},{},{},{},{},{},{ids},{}",
+            report.range.start,
+            report.range.end,
+            report.range.radix,
+            report.width(),
+            report.invalid_count,
+            report.invalid_sum,
+            report.truncated(),
+        )
+        .unwrap();
+    }
+    output
+}
+
+/// IDs, widths and sums are written as strings, as most JSON readers lose precision on numbers above 2^53
+fn render_json(reports: &[RangeReport]) -> String {
+    let rows: Vec<String> = reports
+        .iter()
+        .map(|report| {
+            let ids = report
+                .listed_ids
+                .iter()
+                .map(|id| format!("\"{}\"", id.value))
+                .collect::<Vec<_>>()
+                .join(",");
+            format!(
+                "{{\"start\":\"{}\",\"end\":\"{}\",\"radix\":{},\"width\":\"{}\",\"invalid_count\":{},\"invalid_sum\":\"{}\",\"invalid_ids\":[{ids}],\"truncated\":{}}}",
+                report.range.start,
+                report.range.end,
+                report.range.radix,
+                report.width(),
+                report.invalid_count,
+                report.invalid_sum,
+                report.truncated(),
+            )
+        })
+        .collect();
+    format!("{{\"ranges\":[{}]}}\n", rows.join(","))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse_ranges;
+    use crate::rules::Repeated;
+
+    fn example_reports(limit: usize) -> Vec<RangeReport> {
+        let ranges = parse_ranges("11-22,95-115,998-1012,1698522-1698528,0x10-0x120").unwrap();
+        build(&ranges, 2, &Repeated, limit)
+    }
+
+    #[test]
+    fn test_build() {
+        let reports = example_reports(usize::MAX);
+        let counts: Vec<(u128, u64, u128)> = reports
+            .iter()
+            .map(|report| (report.width(), report.invalid_count, report.invalid_sum))
+            .collect();
+        assert_eq!(
+            counts,
+            vec![
+                (12, 2, 33),
+                (21, 2, 210),
+                (15, 2, 2009),
+                (7, 0, 0),
+                (273, 16, 0x11 * 120 + 0x111)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_text() {
+        let expected = "\
+Range            Width  Invalid   Sum  Invalid IDs
+11-22               12        2    33  11, 22
+95-115              21        2   210  99, 111
+998-1012            15        2  2009  999, 1010
+1698522-1698528      7        0     0
+0x10-0x120         273       16  2313  0x11, 0x22, 0x33, ... (13 more)
+Total              328       22  4565
+";
+        assert_eq!(render(&example_reports(3), Format::Text), expected);
+    }
+
+    #[test]
+    fn test_csv() {
+        let expected = "\
+start,end,radix,width,invalid_count,invalid_sum,invalid_ids,truncated
+11,22,10,12,2,33,11 22,false
+95,115,10,21,2,210,99 111,false
+998,1012,10,15,2,2009,999 1010,false
+1698522,1698528,10,7,0,0,,false
+16,288,16,273,16,2313,17 34,true
+";
+        assert_eq!(render(&example_reports(2), Format::Csv), expected);
+    }
+
+    #[test]
+    fn test_json() {
+        let reports = &example_reports(1)[..2];
+        let expected = concat!(
+            "{\"ranges\":[",
+            "{\"start\":\"11\",\"end\":\"22\",\"radix\":10,\"width\":\"12\",\"invalid_count\":2,\"invalid_sum\":\"33\",\"invalid_ids\":[\"11\"],\"truncated\":true},",
+            "{\"start\":\"95\",\"end\":\"115\",\"radix\":10,\"width\":\"21\",\"invalid_count\":2,\"invalid_sum\":\"210\",\"invalid_ids\":[\"99\"],\"truncated\":true}",
+            "]}\n"
+        );
+        assert_eq!(render(reports, Format::Json), expected);
+
+        // far beyond what a JSON number holds exactly
+        let ranges = parse_ranges(
+            "340282366920934028236692093402823669209-340282366920934028236692093402823669209",
+        )
+        .unwrap();
+        let json = render(&build(&ranges, 1, &Repeated, 1), Format::Json);
+        assert!(json.contains("\"invalid_sum\":\"340282366920934028236692093402823669209\""));
+        assert!(json.contains("\"invalid_ids\":[\"340282366920934028236692093402823669209\"]"));
+    }
+}
@@ -0,0 +1,480 @@
+//! Rules deciding which IDs are invalid, configured from a rule file holding a single expression:
+//!
+//! ```text
+//! # comments run until the end of the line
+//! repeated or (palindrome and not contains "0") or not checksum 7
+//! ```
+//!
+//! An ID is invalid when it matches the expression. `not` binds tightest, then `and`, then `or`.
+//! The built-in rules are:
+//! - `repeated`: the digits are a sequence repeated at least twice, the puzzle's rule
+//! - `palindrome`: the digits read the same backwards
+//! - `increasing` / `decreasing`: every digit is at least / at most the one before it
+//! - `checksum <modulus> [<remainder>]`: the digit sum divided by `modulus` leaves `remainder` (0 by default)
+//! - `contains "<digits>"`: the digits contain the given sequence, written in the ID's radix
+
+use crate::{Id, MAX_DIGITS};
+
+/// A property of IDs, used to decide which ones are invalid
+pub trait Rule: Send + Sync {
+    fn matches(&self, id: &Id) -> bool;
+}
+
+pub struct Repeated;
+
+impl Rule for Repeated {
+    fn matches(&self, id: &Id) -> bool {
+        !id.is_valid()
+    }
+}
+
+pub struct Palindrome;
+
+impl Rule for Palindrome {
+    fn matches(&self, id: &Id) -> bool {
+        let mut buffer = [0; MAX_DIGITS];
+        let digits = id.digits(&mut buffer);
+        digits.iter().eq(digits.iter().rev())
+    }
+}
+
+pub struct Increasing;
+
+impl Rule for Increasing {
+    fn matches(&self, id: &Id) -> bool {
+        let mut buffer = [0; MAX_DIGITS];
+        id.digits(&mut buffer)
+            .windows(2)
+            .all(|pair| pair[0] <= pair[1])
+    }
+}
+
+pub struct Decreasing;
+
+impl Rule for Decreasing {
+    fn matches(&self, id: &Id) -> bool {
+        let mut buffer = [0; MAX_DIGITS];
+        id.digits(&mut buffer)
+            .windows(2)
+            .all(|pair| pair[0] >= pair[1])
+    }
+}
+
+pub struct Checksum {
+    pub modulus: u32,
+    pub remainder: u32,
+}
+
+impl Rule for Checksum {
+    fn matches(&self, id: &Id) -> bool {
+        let mut buffer = [0; MAX_DIGITS];
+        let digit_sum: u32 = id.digits(&mut buffer).iter().map(|&d| d as u32).sum();
+        digit_sum % self.modulus == self.remainder
+    }
+}
+
+/// Matches IDs containing a sequence of digits, given as characters so it works in any radix
+pub struct Contains {
+    /// The digits of the sequence in every radix, `None` where one of them isn't a digit of that radix
+    patterns: Vec<Option<Vec<u8>>>,
+}
+
+impl Contains {
+    pub fn new(digits: &str) -> Self {
+        let patterns = (0..=36)
+            .map(|radix| {
+                if radix < 2 {
+                    return None;
+                }
+                digits
+                    .chars()
+                    .map(|c| c.to_digit(radix).map(|d| d as u8))
+                    .collect()
+            })
+            .collect();
+        Contains { patterns }
+    }
+}
+
+impl Rule for Contains {
+    fn matches(&self, id: &Id) -> bool {
+        let mut buffer = [0; MAX_DIGITS];
+        let Some(pattern) = &self.patterns[id.radix as usize] else {
+            // a digit that doesn't exist in this radix can't be in the ID
+            return false;
+        };
+        let digits = id.digits(&mut buffer);
+        pattern.is_empty()
+            || digits
+                .windows(pattern.len())
+                .any(|window| window == pattern.as_slice())
+    }
+}
+
+pub struct And(pub Box<dyn Rule>, pub Box<dyn Rule>);
+
+impl Rule for And {
+    fn matches(&self, id: &Id) -> bool {
+        self.0.matches(id) && self.1.matches(id)
+    }
+}
+
+pub struct Or(pub Box<dyn Rule>, pub Box<dyn Rule>);
+
+impl Rule for Or {
+    fn matches(&self, id: &Id) -> bool {
+        self.0.matches(id) || self.1.matches(id)
+    }
+}
+
+pub struct Not(pub Box<dyn Rule>);
+
+impl Rule for Not {
+    fn matches(&self, id: &Id) -> bool {
+        !self.0.matches(id)
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum RuleError {
+    UnexpectedEnd,
+    UnexpectedToken(String),
+    UnknownRule(String),
+    InvalidArgument {
+        rule: String,
+        argument: String,
+    },
+    UnterminatedString,
+    /// Parentheses or `not`s nested deeper than `MAX_NESTING`
+    TooDeep,
+}
+
+impl std::fmt::Display for RuleError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            RuleError::UnexpectedEnd => write!(f, "rule ends unexpectedly"),
+            RuleError::UnexpectedToken(token) => write!(f, "unexpected {token:?} in rule"),
+            RuleError::UnknownRule(name) => write!(f, "unknown rule {name:?}"),
+            RuleError::InvalidArgument { rule, argument } => {
+                write!(f, "invalid argument {argument:?} for rule {rule:?}")
+            }
+            RuleError::UnterminatedString => write!(f, "string in rule is never closed"),
+            RuleError::TooDeep => write!(f, "rule is nested more than {MAX_NESTING} levels deep"),
+        }
+    }
+}
+
+impl std::error::Error for RuleError {}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Word(String),
+    Str(String),
+    Open,
+    Close,
+}
+
+impl std::fmt::Display for Token {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Token::Word(word) => write!(f, "{word}"),
+            Token::Str(string) => write!(f, "\"{string}\""),
+            Token::Open => write!(f, "("),
+            Token::Close => write!(f, ")"),
+        }
+    }
+}
+
+fn tokenize(input: &str) -> Result<Vec<Token>, RuleError> {
+    let mut tokens = Vec::new();
+    for line in input.lines() {
+        let mut chars = line.chars().peekable();
+        while let Some(c) = chars.next() {
+            match c {
+                c if c.is_whitespace() => {}
+                // a comment runs until the end of the line, unless it's inside a string
+                '#' => break,
+                '(' => tokens.push(Token::Open),
+                ')' => tokens.push(Token::Close),
+                '"' => {
+                    let mut string = String::new();
+                    loop {
+                        match chars.next() {
+                            Some('"') => break,
+                            Some(c) => string.push(c),
+                            None => return Err(RuleError::UnterminatedString),
+                        }
+                    }
+                    tokens.push(Token::Str(string));
+                }
+                c => {
+                    let mut word = c.to_string();
+                    while let Some(&c) = chars.peek() {
+                        if c.is_whitespace() || "()\"#".contains(c) {
+                            break;
+                        }
+                        word.push(c);
+                        chars.next();
+                    }
+                    tokens.push(Token::Word(word));
+                }
+            }
+        }
+    }
+    Ok(tokens)
+}
+
+/// How deep parentheses and `not`s can nest before parsing them could run out of stack
+const MAX_NESTING: usize = 256;
+
+/// Recursive descent parser over the tokens of a rule file
+struct Parser {
+    tokens: Vec<Token>,
+    position: usize,
+    /// Levels of parentheses and `not`s around the current token
+    depth: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position)
+    }
+
+    fn next(&mut self) -> Result<Token, RuleError> {
+        let token = self.peek().cloned().ok_or(RuleError::UnexpectedEnd)?;
+        self.position += 1;
+        Ok(token)
+    }
+
+    fn next_is_word(&self, word: &str) -> bool {
+        matches!(self.peek(), Some(Token::Word(w)) if w == word)
+    }
+
+    fn or(&mut self) -> Result<Box<dyn Rule>, RuleError> {
+        let mut rule = self.and()?;
+        while self.next_is_word("or") {
+            self.position += 1;
+            rule = Box::new(Or(rule, self.and()?));
+        }
+        Ok(rule)
+    }
+
+    fn and(&mut self) -> Result<Box<dyn Rule>, RuleError> {
+        let mut rule = self.not()?;
+        while self.next_is_word("and") {
+            self.position += 1;
+            rule = Box::new(And(rule, self.not()?));
+        }
+        Ok(rule)
+    }
+
+    fn not(&mut self) -> Result<Box<dyn Rule>, RuleError> {
+        if self.depth == MAX_NESTING {
+            return Err(RuleError::TooDeep);
+        }
+        self.depth += 1;
+        let rule = self.operand();
+        self.depth -= 1;
+        rule
+    }
+
+    fn operand(&mut self) -> Result<Box<dyn Rule>, RuleError> {
+        match self.next()? {
+            Token::Word(word) if word == "not" => Ok(Box::new(Not(self.not()?))),
+            Token::Open => {
+                let rule = self.or()?;
+                match self.next()? {
+                    Token::Close => Ok(rule),
+                    token => Err(RuleError::UnexpectedToken(token.to_string())),
+                }
+            }
+            Token::Word(word) => self.builtin(word),
+            token => Err(RuleError::UnexpectedToken(token.to_string())),
+        }
+    }
+
+    /// A numeric argument of a built-in rule, if there is one
+    fn number(&mut self, rule: &str) -> Result<Option<u32>, RuleError> {
+        let Some(Token::Word(word)) = self.peek() else {
+            return Ok(None);
+        };
+        if !word.starts_with(|c: char| c.is_ascii_digit()) {
+            return Ok(None);
+        }
+        let argument = word.clone();
+        self.position += 1;
+        argument
+            .parse()
+            .map(Some)
+            .map_err(|_| RuleError::InvalidArgument {
+                rule: rule.to_string(),
+                argument,
+            })
+    }
+
+    fn builtin(&mut self, name: String) -> Result<Box<dyn Rule>, RuleError> {
+        let invalid_argument = |argument: String| RuleError::InvalidArgument {
+            rule: name.clone(),
+            argument,
+        };
+        match name.as_str() {
+            "repeated" => Ok(Box::new(Repeated)),
+            "palindrome" => Ok(Box::new(Palindrome)),
+            "increasing" => Ok(Box::new(Increasing)),
+            "decreasing" => Ok(Box::new(Decreasing)),
+            "checksum" => {
+                let modulus = self
+                    .number(&name)?
+                    .filter(|&modulus| modulus > 0)
+                    .ok_or_else(|| invalid_argument("missing or zero modulus".to_string()))?;
+                let remainder = self.number(&name)?.unwrap_or(0);
+                if remainder >= modulus {
+                    return Err(invalid_argument(remainder.to_string()));
+                }
+                Ok(Box::new(Checksum { modulus, remainder }))
+            }
+            "contains" => match self.next()? {
+                Token::Str(digits) => Ok(Box::new(Contains::new(&digits))),
+                token => Err(invalid_argument(token.to_string())),
+            },
+            _ => Err(RuleError::UnknownRule(name)),
+        }
+    }
+}
+
+/// Parse a rule file into the rule it describes
+pub fn parse_rules(input: &str) -> Result<Box<dyn Rule>, RuleError> {
+    let mut parser = Parser {
+        tokens: tokenize(input)?,
+        position: 0,
+        depth: 0,
+    };
+    let rule = parser.or()?;
+    match parser.peek() {
+        None => Ok(rule),
+        Some(token) => Err(RuleError::UnexpectedToken(token.to_string())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn matching(rules: &str, values: impl IntoIterator<Item = u128>) -> Vec<u128> {
+        let rule = parse_rules(rules).unwrap();
+        values
+            .into_iter()
+            .filter(|&value| rule.matches(&Id::new(value)))
+            .collect()
+    }
+
+    #[test]
+    fn test_builtin_rules() {
+        assert_eq!(matching("repeated", 95..=115), vec![99, 111]);
+        assert_eq!(matching("palindrome", 95..=125), vec![99, 101, 111, 121]);
+        assert_eq!(
+            matching("increasing", [123, 1123, 132, 7]),
+            vec![123, 1123, 7]
+        );
+        assert_eq!(
+            matching("decreasing", [321, 3321, 312, 7]),
+            vec![321, 3321, 7]
+        );
+        assert_eq!(matching("checksum 7", [16, 25, 70, 71]), vec![16, 25, 70]);
+        assert_eq!(matching("checksum 7 1", [16, 17, 70]), vec![17]);
+        assert_eq!(
+            matching("contains \"13\"", [13, 113, 131, 31]),
+            vec![13, 113, 131]
+        );
+    }
+
+    #[test]
+    fn test_contains_in_other_radix() {
+        let rule = parse_rules("contains \"AB\"").unwrap();
+        assert!(rule.matches(&Id::new(0xfabf).in_radix(16)));
+        assert!(!rule.matches(&Id::new(0xfabf)));
+    }
+
+    #[test]
+    fn test_composition() {
+        assert_eq!(
+            matching("palindrome and not repeated", 95..=125),
+            vec![101, 121]
+        );
+        assert_eq!(
+            matching("repeated or contains \"0\"", 95..=112),
+            vec![
+                99, 100, 101, 102, 103, 104, 105, 106, 107, 108, 109, 110, 111
+            ]
+        );
+        // `and` binds tighter than `or`, and parentheses override it
+        assert_eq!(
+            matching("repeated or palindrome and checksum 2", 95..=125),
+            vec![99, 101, 111, 121]
+        );
+        assert_eq!(
+            matching("(repeated or palindrome) and checksum 2", 95..=125),
+            vec![99, 101, 121]
+        );
+        assert_eq!(matching("not not repeated", 95..=115), vec![99, 111]);
+    }
+
+    #[test]
+    fn test_rule_file() {
+        let rules =
+            "# the puzzle's rule\nrepeated\n# and unlucky IDs\nor contains \"13\"  # no 13s\n";
+        assert_eq!(matching(rules, 10..=22), vec![11, 13, 22]);
+        // a `#` inside a string isn't a comment, and it isn't a digit either
+        assert_eq!(
+            matching("contains \"1#\" or palindrome#", 10..=12),
+            vec![11]
+        );
+        assert_eq!(matching("repeated# and palindrome", 10..=12), vec![11]);
+    }
+
+    #[test]
+    fn test_nesting_limit() {
+        let nested = |depth: usize| format!("{}repeated{}", "(".repeat(depth), ")".repeat(depth));
+        assert_eq!(matching(&nested(MAX_NESTING - 1), 10..=12), vec![11]);
+        assert_eq!(
+            parse_rules(&nested(MAX_NESTING)).err(),
+            Some(RuleError::TooDeep)
+        );
+        assert_eq!(
+            parse_rules(&nested(100_000)).err(),
+            Some(RuleError::TooDeep)
+        );
+        let nots = format!("{}repeated", "not ".repeat(100_000));
+        assert_eq!(parse_rules(&nots).err(), Some(RuleError::TooDeep));
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        let error = |rules: &str| parse_rules(rules).err().unwrap();
+        assert_eq!(error(""), RuleError::UnexpectedEnd);
+        assert_eq!(error("repeated or"), RuleError::UnexpectedEnd);
+        assert_eq!(error("even"), RuleError::UnknownRule("even".to_string()));
+        assert_eq!(
+            error("repeated palindrome"),
+            RuleError::UnexpectedToken("palindrome".to_string())
+        );
+        assert_eq!(error("(repeated"), RuleError::UnexpectedEnd);
+        assert_eq!(
+            error("repeated and )"),
+            RuleError::UnexpectedToken(")".to_string())
+        );
+        assert_eq!(error("contains \"13"), RuleError::UnterminatedString);
+        assert!(matches!(
+            error("checksum 0"),
+            RuleError::InvalidArgument { .. }
+        ));
+        assert!(matches!(
+            error("checksum 3 3"),
+            RuleError::InvalidArgument { .. }
+        ));
+        assert!(matches!(
+            error("contains 13"),
+            RuleError::InvalidArgument { .. }
+        ));
+    }
+}
@@ -0,0 +1,272 @@
+use crate::{Id, parse_bound, parse_ranges};
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::TcpListener;
+#[cfg(unix)]
+use std::os::unix::fs::FileTypeExt;
+#[cfg(unix)]
+use std::os::unix::net::{UnixListener, UnixStream};
+#[cfg(unix)]
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+
+/// Widest range a client can ask about, so one request can't keep a connection busy for hours
+const MAX_RANGE_WIDTH: u128 = 10_000_000;
+
+/// Longest request line, far more than any range of two `u128`s in binary needs
+const MAX_LINE: usize = 4096;
+
+/// Clients answered at the same time, each one has a thread of its own
+const MAX_CLIENTS: usize = 64;
+
+/// Where the server listens
+#[derive(Debug, PartialEq)]
+pub enum Address {
+    /// A port on localhost, 0 picks a free one
+    Tcp(u16),
+    #[cfg(unix)]
+    Unix(PathBuf),
+}
+
+/// Answer a single request line, which holds one ID or one range
+///
+/// IDs get `<id>: valid` or `<id>: invalid (<repetition>)`, ranges get `<range>: <count> invalid, sum <sum>`
+/// and anything that can't be parsed gets `error: <reason>`.
+pub fn respond(line: &str) -> String {
+    let request = line.trim();
+    if !request.contains('-') {
+        return match parse_bound(request) {
+            Ok((radix, value)) => {
+                let id = Id::new(value).in_radix(radix);
+                match id.analyze() {
+                    Some(repetition) => format!("{id}: invalid ({repetition})"),
+                    None => format!("{id}: valid"),
+                }
+            }
+            Err(e) => format!("error: {e}"),
+        };
+    }
+
+    let range = match parse_ranges(request) {
+        Ok(ranges) => match ranges[..] {
+            [range] => range,
+            _ => return "error: expected one ID or range per line".to_string(),
+        },
+        Err(e) => return format!("error: {}", e.kind),
+    };
+    if range.end - range.start >= MAX_RANGE_WIDTH {
+        return format!("error: range {range} holds more than {MAX_RANGE_WIDTH} IDs");
+    }
+    let (count, sum) = range
+        .ids()
+        .filter(|id| !id.is_valid())
+        .fold((0, 0), |(count, sum), id| (count + 1, sum + id.value));
+    format!("{range}: {count} invalid, sum {sum}")
+}
+
+/// Answer every line read from a client until it hangs up, or sends a line longer than `MAX_LINE`
+fn handle(reader: impl Read, mut writer: impl Write) -> io::Result<()> {
+    let mut reader = BufReader::new(reader);
+    let mut line = Vec::new();
+    loop {
+        line.clear();
+        // reading one byte more than allowed tells a line that's too long from one that just fits
+        let read = (&mut reader)
+            .take(MAX_LINE as u64 + 1)
+            .read_until(b'\n', &mut line)?;
+        if read == 0 {
+            return Ok(());
+        }
+        if read > MAX_LINE && line.last() != Some(&b'\n') {
+            writeln!(writer, "error: line longer than {MAX_LINE} bytes")?;
+            return Ok(());
+        }
+        let line = String::from_utf8_lossy(&line);
+        if line.trim().is_empty() {
+            continue;
+        }
+        writeln!(writer, "{}", respond(&line))?;
+    }
+}
+
+/// One of the `MAX_CLIENTS` clients being answered, freeing its place when dropped
+struct ClientSlot(Arc<AtomicUsize>);
+
+impl ClientSlot {
+    fn take(clients: &Arc<AtomicUsize>) -> Option<Self> {
+        clients
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |count| {
+                (count < MAX_CLIENTS).then_some(count + 1)
+            })
+            .ok()?;
+        Some(ClientSlot(Arc::clone(clients)))
+    }
+}
+
+impl Drop for ClientSlot {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+/// Answer every client from `incoming` on its own thread, turning away the ones beyond `MAX_CLIENTS`
+fn accept<S: Read + Write + Send + 'static>(
+    incoming: impl Iterator<Item = io::Result<S>>,
+    try_clone: impl Fn(&S) -> io::Result<S>,
+) {
+    let clients = Arc::new(AtomicUsize::new(0));
+    for stream in incoming {
+        let streams = stream.and_then(|stream| Ok((try_clone(&stream)?, stream)));
+        // a client hanging up early or running out of file descriptors only affects that client
+        let (reader, mut writer) = match streams {
+            Ok(streams) => streams,
+            Err(e) => {
+                eprintln!("Failed to accept a client: {e}");
+                // errors like running out of file descriptors last a while, don't spin on them
+                std::thread::sleep(Duration::from_millis(10));
+                continue;
+            }
+        };
+        let Some(slot) = ClientSlot::take(&clients) else {
+            let _ = writeln!(
+                writer,
+                "error: more than {MAX_CLIENTS} clients, try again later"
+            );
+            continue;
+        };
+        std::thread::spawn(move || {
+            let _slot = slot;
+            handle(reader, writer)
+        });
+    }
+}
+
+/// Listen on a Unix socket at `path`, replacing the socket file a server that's no longer running left behind
+///
+/// Anything else already at `path`, a live server's socket or a file that isn't a socket, is left alone
+/// and reported.
+#[cfg(unix)]
+fn bind_unix(path: &Path) -> io::Result<UnixListener> {
+    let error = |kind, reason: &str| io::Error::new(kind, format!("{}: {reason}", path.display()));
+    match std::fs::symlink_metadata(path) {
+        Ok(metadata) if !metadata.file_type().is_socket() => {
+            return Err(error(
+                io::ErrorKind::AlreadyExists,
+                "exists and isn't a socket",
+            ));
+        }
+        Ok(_) => {
+            if UnixStream::connect(path).is_ok() {
+                return Err(error(
+                    io::ErrorKind::AddrInUse,
+                    "another server is listening on it",
+                ));
+            }
+            std::fs::remove_file(path)?;
+        }
+        Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+        Err(e) => return Err(e),
+    }
+    UnixListener::bind(path).map_err(|e| error(e.kind(), &e.to_string()))
+}
+
+/// Listen on `address` forever, answering each client on its own thread
+///
+/// Once listening, the address is printed to stdout so a client can find a port picked by the OS.
+pub fn serve(address: &Address) -> io::Result<()> {
+    match address {
+        Address::Tcp(port) => {
+            let listener = TcpListener::bind(("127.0.0.1", *port))?;
+            println!("Listening on {}", listener.local_addr()?);
+            accept(listener.incoming(), |stream| stream.try_clone());
+        }
+        #[cfg(unix)]
+        Address::Unix(path) => {
+            let listener = bind_unix(path)?;
+            println!("Listening on {}", path.display());
+            accept(listener.incoming(), |stream| stream.try_clone());
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_respond_ids() {
+        assert_eq!(respond("1234"), "1234: valid");
+        assert_eq!(respond(" 446446\r"), "446446: invalid (\"446\" x 2)");
+        assert_eq!(respond("0xabab"), "0xabab: invalid (\"ab\" x 2)");
+        assert_eq!(respond("12x"), "error: invalid range bound: \"12x\"");
+    }
+
+    #[test]
+    fn test_respond_ranges() {
+        assert_eq!(respond("11-22"), "11-22: 2 invalid, sum 33");
+        assert_eq!(respond("0x10-0x30"), "0x10-0x30: 2 invalid, sum 51");
+        assert_eq!(
+            respond("22-11"),
+            "error: range \"22-11\" starts after it ends"
+        );
+        assert_eq!(
+            respond("11-22,95-115"),
+            "error: expected one ID or range per line"
+        );
+        assert_eq!(
+            respond("1-100000000"),
+            "error: range 1-100000000 holds more than 10000000 IDs"
+        );
+    }
+
+    #[test]
+    fn test_handle() {
+        let mut output = Vec::new();
+        handle("11\n\n12\n95-115\n".as_bytes(), &mut output).unwrap();
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "11: invalid (\"1\" x 2)\n12: valid\n95-115: 2 invalid, sum 210\n"
+        );
+
+        // the last line doesn't need a newline, and a line of exactly `MAX_LINE` bytes is fine
+        let mut output = Vec::new();
+        let longest = format!("{}\n11", " ".repeat(MAX_LINE - 2) + "22");
+        handle(longest.as_bytes(), &mut output).unwrap();
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "22: invalid (\"2\" x 2)\n11: invalid (\"1\" x 2)\n"
+        );
+
+        // a longer one ends the connection without being read any further
+        let mut output = Vec::new();
+        let input = format!("{}\n11\n", "1".repeat(MAX_LINE + 1));
+        handle(input.as_bytes(), &mut output).unwrap();
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            format!("error: line longer than {MAX_LINE} bytes\n")
+        );
+
+        let mut output = Vec::new();
+        handle(&b"\xff\n11\n"[..], &mut output).unwrap();
+        assert!(
+            String::from_utf8(output)
+                .unwrap()
+                .ends_with("\n11: invalid (\"1\" x 2)\n")
+        );
+    }
+
+    #[test]
+    fn test_client_slots() {
+        let clients = Arc::new(AtomicUsize::new(0));
+        let mut slots: Vec<ClientSlot> = (0..MAX_CLIENTS)
+            .map(|_| ClientSlot::take(&clients).unwrap())
+            .collect();
+        assert!(ClientSlot::take(&clients).is_none());
+        slots.pop();
+        assert!(ClientSlot::take(&clients).is_some());
+        drop(slots);
+        assert_eq!(clients.load(Ordering::SeqCst), 0);
+    }
+}
@@ -0,0 +1,51 @@
+use crate::{Id, parse_bound};
+
+/// Annotate each ID in a comma or whitespace separated list with whether it's valid,
+/// and the nearest valid IDs for the ones that aren't
+pub fn annotate(input: &str) -> Vec<String> {
+    input
+        .split(|c: char| c == ',' || c.is_whitespace())
+        .filter(|item| !item.is_empty())
+        .map(annotate_one)
+        .collect()
+}
+
+fn annotate_one(item: &str) -> String {
+    let id = match parse_bound(item) {
+        Ok((radix, value)) => Id::new(value).in_radix(radix),
+        Err(e) => return format!("{item}: {e}"),
+    };
+    let Some(repetition) = id.analyze() else {
+        return format!("{id}: valid");
+    };
+    let nearest = id.nearest_valid();
+    let suggestions: Vec<String> = [nearest.below, nearest.above]
+        .into_iter()
+        .flatten()
+        .map(|id| id.to_string())
+        .collect();
+    format!(
+        "{id}: invalid ({repetition}), nearest valid: {}",
+        suggestions.join(" or ")
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_annotate() {
+        assert_eq!(
+            annotate("446446, 1234\n0xabab 0b11,12x"),
+            vec![
+                "446446: invalid (\"446\" x 2), nearest valid: 446445 or 446447",
+                "1234: valid",
+                "0xabab: invalid (\"ab\" x 2), nearest valid: 0xabaa or 0xabac",
+                "0b11: invalid (\"1\" x 2), nearest valid: 0b10 or 0b100",
+                "12x: invalid range bound: \"12x\"",
+            ]
+        );
+        assert!(annotate(" \n,").is_empty());
+    }
+}
@@ -0,0 +1,124 @@
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::TcpStream;
+#[cfg(unix)]
+use std::os::unix::net::UnixStream;
+use std::process::{Child, Command, Stdio};
+
+/// A running `day02 serve`, killed when dropped so a failing test doesn't leave it behind
+struct Server {
+    child: Child,
+    address: String,
+}
+
+impl Server {
+    fn start(args: &[&str]) -> Self {
+        let mut child = Command::new(env!("CARGO_BIN_EXE_day02"))
+            .arg("serve")
+            .args(args)
+            .stdout(Stdio::piped())
+            .spawn()
+            .expect("Failed to start the server");
+        // the server only prints its address once it's listening
+        let mut line = String::new();
+        BufReader::new(child.stdout.take().unwrap())
+            .read_line(&mut line)
+            .unwrap();
+        let address = line
+            .trim()
+            .strip_prefix("Listening on ")
+            .unwrap_or_else(|| panic!("Unexpected server output: {line:?}"))
+            .to_string();
+        Server { child, address }
+    }
+}
+
+impl Drop for Server {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+/// Send every request, then read one reply line per request
+fn ask(stream: impl Read + Write, requests: &[&str]) -> Vec<String> {
+    let mut reader = BufReader::new(stream);
+    for request in requests {
+        writeln!(reader.get_mut(), "{request}").unwrap();
+    }
+    requests
+        .iter()
+        .map(|_| {
+            let mut line = String::new();
+            reader.read_line(&mut line).unwrap();
+            line.trim_end().to_string()
+        })
+        .collect()
+}
+
+#[test]
+fn test_tcp() {
+    let server = Server::start(&["--tcp", "0"]);
+    let replies = ask(
+        TcpStream::connect(&server.address).unwrap(),
+        &["1234", "446446", "11-22", "22-11", "oops"],
+    );
+    assert_eq!(
+        replies,
+        vec![
+            "1234: valid",
+            "446446: invalid (\"446\" x 2)",
+            "11-22: 2 invalid, sum 33",
+            "error: range \"22-11\" starts after it ends",
+            "error: invalid range bound: \"oops\"",
+        ]
+    );
+
+    // clients are served at the same time, and one hanging up doesn't affect the others
+    let first = TcpStream::connect(&server.address).unwrap();
+    let second = TcpStream::connect(&server.address).unwrap();
+    assert_eq!(
+        ask(second, &["0x1111"]),
+        vec!["0x1111: invalid (\"1\" x 4)"]
+    );
+    assert_eq!(ask(first, &["95-115"]), vec!["95-115: 2 invalid, sum 210"]);
+}
+
+#[test]
+#[cfg(unix)]
+fn test_unix_socket() {
+    let path = std::env::temp_dir().join(format!("day02-test-{}.sock", std::process::id()));
+    let _ = std::fs::remove_file(&path);
+    let server = Server::start(&["--unix", path.to_str().unwrap()]);
+    assert_eq!(server.address, path.display().to_string());
+
+    let replies = ask(UnixStream::connect(&path).unwrap(), &["99", "100"]);
+    assert_eq!(replies, vec!["99: invalid (\"9\" x 2)", "100: valid"]);
+
+    // a server that's running keeps its socket
+    let second = Command::new(env!("CARGO_BIN_EXE_day02"))
+        .args(["serve", "--unix", path.to_str().unwrap()])
+        .output()
+        .unwrap();
+    assert!(!second.status.success());
+    assert!(String::from_utf8_lossy(&second.stderr).contains("another server is listening on it"));
+
+    // but the socket file of one that was killed is replaced
+    drop(server);
+    assert!(path.exists());
+    let server = Server::start(&["--unix", path.to_str().unwrap()]);
+    let replies = ask(UnixStream::connect(&path).unwrap(), &["1010"]);
+    assert_eq!(replies, vec!["1010: invalid (\"10\" x 2)"]);
+
+    drop(server);
+    std::fs::remove_file(&path).unwrap();
+
+    // and anything else at the path is left alone
+    std::fs::write(&path, "not a socket").unwrap();
+    let output = Command::new(env!("CARGO_BIN_EXE_day02"))
+        .args(["serve", "--unix", path.to_str().unwrap()])
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    assert_eq!(std::fs::read_to_string(&path).unwrap(), "not a socket");
+    std::fs::remove_file(&path).unwrap();
+}
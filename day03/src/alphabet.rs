@@ -0,0 +1,153 @@
+use crate::joltage::Joltage;
+
+/// Highest base a bank can be written in, one battery per `0`-`9` and `A`-`Z`
+pub const MAX_RADIX: u32 = 36;
+
+/// How battery ratings are written in a bank, and so the base its joltage is written in
+#[derive(Debug, Clone, PartialEq)]
+pub enum Alphabet {
+    /// `0`-`9` then `A`-`Z` for the digits of the base, in either case
+    Radix(u32),
+    /// Every symbol rates as its position in the list, matched exactly
+    Symbols(Vec<char>),
+}
+
+impl Default for Alphabet {
+    fn default() -> Self {
+        Alphabet::Radix(10)
+    }
+}
+
+impl Alphabet {
+    pub fn radix(radix: u32) -> Result<Self, String> {
+        if !(2..=MAX_RADIX).contains(&radix) {
+            return Err(format!("Radix {radix} isn't between 2 and {MAX_RADIX}"));
+        }
+        Ok(Alphabet::Radix(radix))
+    }
+
+    /// The symbols in increasing order of rating
+    pub fn symbols(symbols: &str) -> Result<Self, String> {
+        let symbols: Vec<char> = symbols.chars().collect();
+        if !(2..=MAX_RADIX as usize).contains(&symbols.len()) {
+            return Err(format!(
+                "An alphabet needs between 2 and {MAX_RADIX} symbols, not {}",
+                symbols.len()
+            ));
+        }
+        if let Some((index, &symbol)) = symbols
+            .iter()
+            .enumerate()
+            .find(|&(index, symbol)| symbols[..index].contains(symbol))
+        {
+            return Err(format!("Symbol {symbol:?} repeats at position {index}"));
+        }
+        if let Some((index, symbol)) = symbols
+            .iter()
+            .enumerate()
+            .find(|(_, symbol)| symbol.is_whitespace())
+        {
+            return Err(format!(
+                "Symbol {symbol:?} at position {index} is whitespace, which bank lines are trimmed of"
+            ));
+        }
+        Ok(Alphabet::Symbols(symbols))
+    }
+
+    /// The base of the ratings, and of the joltages made from them
+    pub fn base(&self) -> u32 {
+        match self {
+            Alphabet::Radix(radix) => *radix,
+            Alphabet::Symbols(symbols) => symbols.len() as u32,
+        }
+    }
+
+    /// Rating of `symbol`, if it's in the alphabet
+    pub fn digit(&self, symbol: char) -> Option<u8> {
+        match self {
+            Alphabet::Radix(radix) => symbol.to_digit(*radix).map(|digit| digit as u8),
+            Alphabet::Symbols(symbols) => symbols
+                .iter()
+                .position(|&candidate| candidate == symbol)
+                .map(|digit| digit as u8),
+        }
+    }
+
+    pub fn symbol(&self, digit: u8) -> char {
+        match self {
+            Alphabet::Radix(radix) => char::from_digit(u32::from(digit), *radix)
+                .expect("Ratings are below the radix")
+                .to_ascii_uppercase(),
+            Alphabet::Symbols(symbols) => symbols[usize::from(digit)],
+        }
+    }
+
+    /// The joltage written with the symbols of the alphabet
+    pub fn format(&self, joltage: &Joltage) -> String {
+        joltage
+            .digits_in(self.base())
+            .into_iter()
+            .map(|digit| self.symbol(digit))
+            .collect()
+    }
+
+    /// Parse a joltage written with the symbols of the alphabet
+    pub fn parse(&self, value: &str) -> Result<Joltage, String> {
+        let digits = value
+            .chars()
+            .map(|symbol| self.digit(symbol))
+            .collect::<Option<Vec<u8>>>()
+            .filter(|digits| !digits.is_empty())
+            .ok_or_else(|| format!("Invalid base {} joltage: {value:?}", self.base()))?;
+        Ok(Joltage::from_digits_in(&digits, self.base()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_radix() {
+        let hex = Alphabet::radix(16).unwrap();
+        assert_eq!(hex.base(), 16);
+        assert_eq!(hex.digit('f'), Some(15));
+        assert_eq!(hex.digit('F'), Some(15));
+        assert_eq!(hex.digit('g'), None);
+        assert_eq!(hex.symbol(11), 'B');
+        assert_eq!(Alphabet::radix(36).unwrap().digit('z'), Some(35));
+        assert!(Alphabet::radix(1).is_err());
+        assert!(Alphabet::radix(37).is_err());
+    }
+
+    #[test]
+    fn test_symbols() {
+        let alphabet = Alphabet::symbols("-+*").unwrap();
+        assert_eq!(alphabet.base(), 3);
+        assert_eq!(alphabet.digit('*'), Some(2));
+        assert_eq!(alphabet.digit('0'), None);
+        assert_eq!(alphabet.symbol(1), '+');
+        assert!(Alphabet::symbols("x").is_err());
+        assert!(Alphabet::symbols("abca").is_err());
+        assert!(Alphabet::symbols(" ab").is_err());
+        assert!(Alphabet::symbols("a\tb").is_err());
+        assert!(Alphabet::symbols(&"x".repeat(37)).is_err());
+    }
+
+    #[test]
+    fn test_format_and_parse() {
+        let hex = Alphabet::radix(16).unwrap();
+        let joltage = hex.parse("ff0").unwrap();
+        assert_eq!(joltage.to_string(), "4080");
+        assert_eq!(hex.format(&joltage), "FF0");
+        assert_eq!(hex.format(&Joltage::default()), "0");
+        assert!(hex.parse("").is_err());
+        assert!(hex.parse("fg").is_err());
+
+        let alphabet = Alphabet::symbols("-+*").unwrap();
+        let joltage = alphabet.parse("*+-").unwrap();
+        assert_eq!(joltage.to_string(), (2 * 9 + 3).to_string());
+        assert_eq!(alphabet.format(&joltage), "*+-");
+        assert_eq!(Alphabet::default().format(&joltage), joltage.to_string());
+    }
+}
@@ -0,0 +1,276 @@
+use crate::joltage::Joltage;
+use crate::{Bank, Mode, Selection};
+
+/// How many batteries to enable in total, and how many each bank may take
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Budget {
+    pub total: usize,
+    pub min_per_bank: usize,
+    pub max_per_bank: usize,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum BudgetError {
+    /// A bank has fewer batteries than every bank has to enable
+    BankTooShort { bank: usize, length: usize },
+    /// The total can't be split over the banks within the per bank limits
+    OutOfRange {
+        total: usize,
+        min_total: usize,
+        max_total: usize,
+    },
+}
+
+impl std::fmt::Display for BudgetError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            BudgetError::BankTooShort { bank, length } => {
+                write!(f, "bank {bank} only has {length} batteries")
+            }
+            BudgetError::OutOfRange {
+                total,
+                min_total,
+                max_total,
+            } => write!(
+                f,
+                "can't enable {total} batteries, the banks can take between {min_total} and {max_total}"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for BudgetError {}
+
+/// The selection picked in every bank, in the order of the banks
+#[derive(Debug)]
+pub struct Allocation {
+    pub selections: Vec<Selection>,
+    pub total: Joltage,
+}
+
+/// Spread exactly `budget.total` enabled batteries over the banks for the highest total joltage
+///
+/// The best joltage of a bank only depends on how many batteries it gets, so after finding it for every
+/// allowed count, a table over the banks keeps the best total for every number of batteries used so far.
+pub fn allocate(banks: &[Bank], budget: &Budget) -> Result<Allocation, BudgetError> {
+    assert!(
+        budget.min_per_bank <= budget.max_per_bank,
+        "Banks can't take at least {} and at most {} batteries",
+        budget.min_per_bank,
+        budget.max_per_bank
+    );
+    // best selection of each bank for every count it may take, starting at min_per_bank
+    let mut options: Vec<Vec<Selection>> = Vec::with_capacity(banks.len());
+    for (index, bank) in banks.iter().enumerate() {
+        let length = bank.batteries.len();
+        if length < budget.min_per_bank {
+            return Err(BudgetError::BankTooShort {
+                bank: index,
+                length,
+            });
+        }
+        let max = budget.max_per_bank.min(length);
+        options.push(
+            (budget.min_per_bank..=max)
+                .map(|count| bank.select(count, &Mode::Max).expect("Count fits the bank"))
+                .collect(),
+        );
+    }
+    let min_total = budget.min_per_bank * banks.len();
+    let max_total: usize = options
+        .iter()
+        .map(|counts| budget.min_per_bank + counts.len() - 1)
+        .sum();
+    if !(min_total..=max_total).contains(&budget.total) {
+        return Err(BudgetError::OutOfRange {
+            total: budget.total,
+            min_total,
+            max_total,
+        });
+    }
+
+    // best[used] is the best total of the banks so far using `used` batteries, and choices[bank][used]
+    // the index into the options of that bank that got there
+    let mut best: Vec<Option<Joltage>> = vec![None; budget.total + 1];
+    best[0] = Some(Joltage::default());
+    let mut choices: Vec<Vec<usize>> = Vec::with_capacity(banks.len());
+    for bank_options in &options {
+        let mut next: Vec<Option<Joltage>> = vec![None; budget.total + 1];
+        let mut bank_choices = vec![0; budget.total + 1];
+        for (used, total) in best.iter().enumerate() {
+            let Some(total) = total else { continue };
+            for (option, selection) in bank_options.iter().enumerate() {
+                let count = budget.min_per_bank + option;
+                let Some(slot) = next.get_mut(used + count) else {
+                    break;
+                };
+                let candidate = total.clone() + &selection.joltage;
+                if slot.as_ref().is_none_or(|current| candidate > *current) {
+                    *slot = Some(candidate);
+                    bank_choices[used + count] = option;
+                }
+            }
+        }
+        best = next;
+        choices.push(bank_choices);
+    }
+
+    let total = best[budget.total]
+        .take()
+        .expect("A total within range can always be reached");
+    // walk back from the last bank to find the count each bank got
+    let mut used = budget.total;
+    let mut picks = vec![0; banks.len()];
+    for (bank, bank_choices) in choices.iter().enumerate().rev() {
+        picks[bank] = bank_choices[used];
+        used -= budget.min_per_bank + picks[bank];
+    }
+    let selections = options
+        .into_iter()
+        .zip(picks)
+        .map(|(mut bank_options, pick)| bank_options.swap_remove(pick))
+        .collect();
+    Ok(Allocation { selections, total })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::Rng;
+
+    fn banks(lines: &[&str]) -> Vec<Bank> {
+        lines.iter().map(|line| Bank::new(line).unwrap()).collect()
+    }
+
+    fn counts(allocation: &Allocation) -> Vec<usize> {
+        allocation
+            .selections
+            .iter()
+            .map(|selection| selection.indices.len())
+            .collect()
+    }
+
+    #[test]
+    fn test_allocate() {
+        let banks = banks(&["91", "1111", "8"]);
+        let budget = Budget {
+            total: 3,
+            min_per_bank: 0,
+            max_per_bank: 4,
+        };
+        // a third digit is worth more than the best two digit sum 91 + 8
+        let allocation = allocate(&banks, &budget).unwrap();
+        assert_eq!(counts(&allocation), vec![0, 3, 0]);
+        assert_eq!(allocation.total.to_string(), "111");
+
+        let budget = Budget { total: 2, ..budget };
+        let allocation = allocate(&banks, &budget).unwrap();
+        assert_eq!(counts(&allocation), vec![2, 0, 0]);
+        assert_eq!(allocation.total.to_string(), "91");
+
+        let budget = Budget { total: 4, ..budget };
+        let allocation = allocate(&banks, &budget).unwrap();
+        assert_eq!(counts(&allocation), vec![0, 4, 0]);
+        assert_eq!(allocation.total.to_string(), "1111");
+
+        let budget = Budget {
+            total: 4,
+            min_per_bank: 1,
+            max_per_bank: 2,
+        };
+        let allocation = allocate(&banks, &budget).unwrap();
+        assert_eq!(counts(&allocation), vec![2, 1, 1]);
+        assert_eq!(allocation.total.to_string(), (91 + 1 + 8).to_string());
+    }
+
+    #[test]
+    fn test_errors() {
+        let banks = banks(&["91", "1111", "8"]);
+        let budget = Budget {
+            total: 6,
+            min_per_bank: 1,
+            max_per_bank: 2,
+        };
+        assert_eq!(
+            allocate(&banks, &budget).unwrap_err(),
+            BudgetError::OutOfRange {
+                total: 6,
+                min_total: 3,
+                max_total: 5
+            }
+        );
+        let budget = Budget { total: 2, ..budget };
+        assert!(matches!(
+            allocate(&banks, &budget),
+            Err(BudgetError::OutOfRange { min_total: 3, .. })
+        ));
+        let budget = Budget {
+            total: 6,
+            min_per_bank: 2,
+            max_per_bank: 2,
+        };
+        assert_eq!(
+            allocate(&banks, &budget).unwrap_err(),
+            BudgetError::BankTooShort { bank: 2, length: 1 }
+        );
+    }
+
+    /// Try every way to split the budget, each bank taking its best selection for its count
+    fn brute_force_total(banks: &[Bank], budget: &Budget) -> Option<u64> {
+        let Some((first, rest)) = banks.split_first() else {
+            return (budget.total == 0).then_some(0);
+        };
+        let max = budget.max_per_bank.min(first.batteries.len());
+        (budget.min_per_bank..=max.min(budget.total))
+            .filter_map(|count| {
+                let joltage: u64 = first
+                    .select(count, &Mode::Max)
+                    .unwrap()
+                    .joltage
+                    .to_string()
+                    .parse()
+                    .unwrap();
+                let rest_budget = Budget {
+                    total: budget.total - count,
+                    ..*budget
+                };
+                Some(joltage + brute_force_total(rest, &rest_budget)?)
+            })
+            .max()
+    }
+
+    #[test]
+    fn test_matches_brute_force() {
+        let mut rng = Rng(0xb0d6_e700_0000_0046);
+        for _ in 0..300 {
+            let lines: Vec<String> = (0..1 + rng.below(4))
+                .map(|_| {
+                    (0..1 + rng.below(6))
+                        .map(|_| (b'0' + rng.below(10) as u8) as char)
+                        .collect()
+                })
+                .collect();
+            let banks: Vec<Bank> = lines.iter().map(|line| Bank::new(line).unwrap()).collect();
+            let min_per_bank = rng.below(2);
+            let budget = Budget {
+                total: rng.below(16),
+                min_per_bank,
+                max_per_bank: min_per_bank + rng.below(4),
+            };
+            let expected = brute_force_total(&banks, &budget);
+            match allocate(&banks, &budget) {
+                Ok(allocation) => {
+                    assert_eq!(
+                        Some(allocation.total.to_string()),
+                        expected.map(|total| total.to_string()),
+                        "{lines:?}, {budget:?}"
+                    );
+                    assert_eq!(counts(&allocation).iter().sum::<usize>(), budget.total);
+                    let sum: Joltage = allocation.selections.iter().map(|s| &s.joltage).sum();
+                    assert_eq!(sum, allocation.total);
+                }
+                Err(_) => assert_eq!(expected, None, "{lines:?}, {budget:?}"),
+            }
+        }
+    }
+}
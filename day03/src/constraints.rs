@@ -0,0 +1,325 @@
+/// At most `max` enabled batteries in each run of `length` batteries, counted from the start of the bank
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SegmentCap {
+    pub length: usize,
+    pub max: usize,
+}
+
+/// Physical limits on which batteries can be enabled together
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Constraints {
+    /// Disabled batteries needed between two enabled ones, 1 keeps neighbours from both being enabled
+    pub min_gap: usize,
+    /// Positions of faulty batteries that can't be enabled at all
+    pub disabled: Vec<usize>,
+    pub segment_cap: Option<SegmentCap>,
+}
+
+impl Constraints {
+    /// Parse a `<length>:<max>` segment cap
+    pub fn parse_segment_cap(value: &str) -> Result<SegmentCap, String> {
+        let error = || format!("Invalid segment cap: {value:?}, expected <length>:<max>");
+        let (length, max) = value.split_once(':').ok_or_else(error)?;
+        let length = length.parse().ok().filter(|&length| length > 0);
+        let max = max.parse().ok();
+        match (length, max) {
+            (Some(length), Some(max)) => Ok(SegmentCap { length, max }),
+            _ => Err(error()),
+        }
+    }
+}
+
+/// Memoized answers to "can `remaining` more batteries still be enabled from here on?"
+struct Feasibility<'a> {
+    allowed: Vec<bool>,
+    count: usize,
+    constraints: &'a Constraints,
+    segment: SegmentCap,
+    /// Indexed by position, remaining count and batteries already enabled in the position's segment
+    memo: Vec<Option<bool>>,
+}
+
+impl<'a> Feasibility<'a> {
+    fn new(length: usize, count: usize, constraints: &'a Constraints) -> Self {
+        let mut allowed = vec![true; length];
+        for &position in &constraints.disabled {
+            if let Some(allowed) = allowed.get_mut(position) {
+                *allowed = false;
+            }
+        }
+        // without a cap, the whole bank is one segment that can be filled completely
+        let mut segment = constraints.segment_cap.unwrap_or(SegmentCap {
+            length: length.max(1),
+            max: length,
+        });
+        // a segment can't hold more than its length, nor the selection more than `count`, so any cap
+        // above those is no cap at all, and keeping it would only blow up the table
+        segment.max = segment.max.min(segment.length).min(count);
+        let states = (length + 1) * (count + 1) * (segment.max + 1);
+        Feasibility {
+            allowed,
+            count,
+            constraints,
+            segment,
+            memo: vec![None; states],
+        }
+    }
+
+    /// Batteries already enabled in the segment of `to`, after being at `from` with `used` enabled in its segment
+    fn used_at(&self, from: usize, used: usize, to: usize) -> usize {
+        if from / self.segment.length == to / self.segment.length {
+            used
+        } else {
+            0
+        }
+    }
+
+    /// Whether the battery at `position` can be enabled, with `used` already enabled in its segment
+    fn can_enable(&self, position: usize, used: usize) -> bool {
+        self.allowed[position] && used < self.segment.max
+    }
+
+    /// First position that can be enabled after enabling `position`
+    fn next_after(&self, position: usize) -> usize {
+        (position + self.constraints.min_gap + 1).min(self.allowed.len())
+    }
+
+    fn feasible(&mut self, position: usize, remaining: usize, used: usize) -> bool {
+        if remaining == 0 {
+            return true;
+        }
+        if position >= self.allowed.len() {
+            return false;
+        }
+        let key = (position * (self.count + 1) + remaining) * (self.segment.max + 1) + used;
+        if let Some(feasible) = self.memo[key] {
+            return feasible;
+        }
+        let feasible = (self.can_enable(position, used) && {
+            let next = self.next_after(position);
+            let used = self.used_at(position, used + 1, next);
+            self.feasible(next, remaining - 1, used)
+        }) || {
+            let used = self.used_at(position, used, position + 1);
+            self.feasible(position + 1, remaining, used)
+        };
+        self.memo[key] = Some(feasible);
+        feasible
+    }
+}
+
+/// Indices of the `count` batteries giving the maximum joltage within `constraints`, if there are any
+///
+/// Every selection has `count` digits, so the highest joltage is the one with the highest digits from
+/// the left. Each next battery is the highest digit after which the rest of the selection can still be
+/// completed, which the dynamic programming table answers, taking the leftmost one on ties since that
+/// leaves the most room for the later picks.
+pub fn max_selection(digits: &[u8], count: usize, constraints: &Constraints) -> Option<Vec<usize>> {
+    let mut feasibility = Feasibility::new(digits.len(), count, constraints);
+    if !feasibility.feasible(0, count, 0) {
+        return None;
+    }
+
+    let mut indices = Vec::with_capacity(count);
+    let (mut position, mut used) = (0, 0);
+    for remaining in (0..count).rev() {
+        let mut best: Option<usize> = None;
+        for candidate in position..digits.len() {
+            let candidate_used = feasibility.used_at(position, used, candidate);
+            if best.is_some_and(|best| digits[best] >= digits[candidate])
+                || !feasibility.can_enable(candidate, candidate_used)
+            {
+                continue;
+            }
+            let next = feasibility.next_after(candidate);
+            let next_used = feasibility.used_at(candidate, candidate_used + 1, next);
+            if feasibility.feasible(next, remaining, next_used) {
+                best = Some(candidate);
+            }
+        }
+        let best = best.expect("A feasible state always has a next battery");
+        indices.push(best);
+        used = feasibility.used_at(position, used, best) + 1;
+        position = feasibility.next_after(best);
+        used = feasibility.used_at(best, used, position);
+    }
+    Some(indices)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::{Rng, all_picks, digits};
+
+    /// Digits of the picked batteries
+    fn picked(bank: &str, indices: &[usize]) -> String {
+        indices.iter().map(|&index| &bank[index..=index]).collect()
+    }
+
+    #[test]
+    fn test_no_constraints() {
+        let bank = "818181911112111";
+        let indices = max_selection(&digits(bank), 12, &Constraints::default()).unwrap();
+        assert_eq!(picked(bank, &indices), "888911112111");
+        assert_eq!(
+            max_selection(&digits(bank), 0, &Constraints::default()),
+            Some(vec![])
+        );
+        assert_eq!(
+            max_selection(&digits(bank), 16, &Constraints::default()),
+            None
+        );
+    }
+
+    #[test]
+    fn test_min_gap() {
+        let bank = "9989";
+        let constraints = Constraints {
+            min_gap: 1,
+            ..Constraints::default()
+        };
+        // 99 can't use both leading nines, but it can skip the 8
+        assert_eq!(
+            max_selection(&digits(bank), 2, &constraints),
+            Some(vec![0, 3])
+        );
+        assert_eq!(
+            max_selection(&digits("9981"), 2, &constraints),
+            Some(vec![0, 2])
+        );
+        assert_eq!(max_selection(&digits(bank), 3, &constraints), None);
+
+        let constraints = Constraints {
+            min_gap: 2,
+            ..Constraints::default()
+        };
+        assert_eq!(
+            max_selection(&digits(bank), 2, &constraints),
+            Some(vec![0, 3])
+        );
+    }
+
+    #[test]
+    fn test_disabled() {
+        let bank = "9989";
+        let constraints = Constraints {
+            disabled: vec![0, 3, 100],
+            ..Constraints::default()
+        };
+        assert_eq!(
+            max_selection(&digits(bank), 2, &constraints),
+            Some(vec![1, 2])
+        );
+        assert_eq!(max_selection(&digits(bank), 3, &constraints), None);
+    }
+
+    #[test]
+    fn test_segment_cap() {
+        let bank = "99119911";
+        let constraints = Constraints {
+            segment_cap: Some(SegmentCap { length: 4, max: 1 }),
+            ..Constraints::default()
+        };
+        assert_eq!(
+            max_selection(&digits(bank), 2, &constraints),
+            Some(vec![0, 4])
+        );
+        assert_eq!(max_selection(&digits(bank), 3, &constraints), None);
+
+        let constraints = Constraints {
+            segment_cap: Some(SegmentCap { length: 3, max: 2 }),
+            ..Constraints::default()
+        };
+        // segments are 991, 199 and 11
+        let indices = max_selection(&digits(bank), 4, &constraints).unwrap();
+        assert_eq!(picked(bank, &indices), "9999");
+    }
+
+    #[test]
+    fn test_huge_segment_cap() {
+        let bank = "818181911112111";
+        let expected = max_selection(&digits(bank), 12, &Constraints::default());
+        for cap in ["1:18446744073709551615", "10:1000000000", "100000:100000"] {
+            let constraints = Constraints {
+                segment_cap: Some(Constraints::parse_segment_cap(cap).unwrap()),
+                ..Constraints::default()
+            };
+            // every one of these caps allows at least a whole segment, which is no cap at all
+            assert_eq!(
+                max_selection(&digits(bank), 12, &constraints),
+                expected,
+                "{cap}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_parse_segment_cap() {
+        assert_eq!(
+            Constraints::parse_segment_cap("10:3"),
+            Ok(SegmentCap { length: 10, max: 3 })
+        );
+        assert!(Constraints::parse_segment_cap("0:3").is_err());
+        assert!(Constraints::parse_segment_cap("10").is_err());
+        assert!(Constraints::parse_segment_cap("10:-1").is_err());
+    }
+
+    /// Every selection of `count` batteries that respects `constraints`, as a joltage
+    fn brute_force_max(digits: &[u8], count: usize, constraints: &Constraints) -> Option<u64> {
+        all_picks(digits.len(), count)
+            .into_iter()
+            .filter(|indices| {
+                let spaced = indices
+                    .windows(2)
+                    .all(|pair| pair[1] - pair[0] > constraints.min_gap);
+                let working = indices
+                    .iter()
+                    .all(|index| !constraints.disabled.contains(index));
+                let capped = constraints.segment_cap.is_none_or(|cap| {
+                    (0..digits.len().div_ceil(cap.length)).all(|segment| {
+                        indices
+                            .iter()
+                            .filter(|&&index| index / cap.length == segment)
+                            .count()
+                            <= cap.max
+                    })
+                });
+                spaced && working && capped
+            })
+            .map(|indices| {
+                indices
+                    .iter()
+                    .fold(0, |joltage, &index| joltage * 10 + u64::from(digits[index]))
+            })
+            .max()
+    }
+
+    #[test]
+    fn test_matches_brute_force() {
+        let mut rng = Rng(0xc0a5_7a1a_7500_0045);
+        let mut below = |len: usize| rng.below(len);
+        for _ in 0..2000 {
+            let length = 1 + below(12);
+            let digits: Vec<u8> = (0..length).map(|_| below(10) as u8).collect();
+            let count = below(length + 1);
+            let constraints = Constraints {
+                min_gap: below(3),
+                disabled: (0..below(3)).map(|_| below(length)).collect(),
+                segment_cap: (below(2) == 0).then(|| SegmentCap {
+                    length: 1 + below(5),
+                    max: below(3),
+                }),
+            };
+            let selection = max_selection(&digits, count, &constraints).map(|indices| {
+                indices
+                    .iter()
+                    .fold(0, |joltage, &index| joltage * 10 + u64::from(digits[index]))
+            });
+            assert_eq!(
+                selection,
+                brute_force_max(&digits, count, &constraints),
+                "{digits:?}, {count} batteries, {constraints:?}"
+            );
+        }
+    }
+}
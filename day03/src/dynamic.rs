@@ -0,0 +1,220 @@
+use crate::alphabet::Alphabet;
+use crate::joltage::Joltage;
+use crate::{Bank, Battery, Selection, SelectionError};
+
+/// A bank whose batteries can be swapped, answering maximum joltage queries without rescanning it
+///
+/// A segment tree keeps the position of the leftmost highest battery of every node, so each greedy
+/// pick of the maximum selection is one range query instead of a scan of its whole window.
+pub struct DynamicBank {
+    batteries: Vec<Battery>,
+    /// Leaves start at `leaves`, node `i` has children `2i` and `2i + 1`, and unused leaves hold `None`
+    tree: Vec<Option<usize>>,
+    leaves: usize,
+    alphabet: Alphabet,
+}
+
+impl DynamicBank {
+    pub fn new(bank: Bank) -> Self {
+        let leaves = bank.batteries.len().next_power_of_two();
+        let mut dynamic = DynamicBank {
+            batteries: bank.batteries,
+            tree: vec![None; 2 * leaves],
+            leaves,
+            alphabet: bank.alphabet,
+        };
+        for index in 0..dynamic.batteries.len() {
+            dynamic.tree[leaves + index] = Some(index);
+        }
+        for node in (1..leaves).rev() {
+            dynamic.tree[node] = dynamic.best(dynamic.tree[2 * node], dynamic.tree[2 * node + 1]);
+        }
+        dynamic
+    }
+
+    pub fn len(&self) -> usize {
+        self.batteries.len()
+    }
+
+    /// Swap the battery at `index` for one rated `digit`
+    pub fn set_digit(&mut self, index: usize, digit: u8) {
+        assert!(
+            u32::from(digit) < self.alphabet.base(),
+            "Batteries are rated with a single base {} digit, not {digit}",
+            self.alphabet.base()
+        );
+        self.batteries[index].digit = digit;
+        let mut node = (self.leaves + index) / 2;
+        while node > 0 {
+            self.tree[node] = self.best(self.tree[2 * node], self.tree[2 * node + 1]);
+            node /= 2;
+        }
+    }
+
+    /// Same selection as `Bank::select` with `Mode::Max`, in O(count log n)
+    pub fn max_joltage(&self, count: usize) -> Result<Selection, SelectionError> {
+        let length = self.batteries.len();
+        if count > length {
+            return Err(SelectionError::NotEnoughBatteries {
+                requested: count,
+                available: length,
+            });
+        }
+        let mut indices = Vec::with_capacity(count);
+        let mut start = 0;
+        for remaining in (0..count).rev() {
+            let best = self
+                .query(start, length - remaining)
+                .expect("The window always holds a battery");
+            indices.push(best);
+            start = best + 1;
+        }
+        let digits: Vec<u8> = indices
+            .iter()
+            .map(|&index| self.batteries[index].digit)
+            .collect();
+        let joltage = Joltage::from_digits_in(&digits, self.alphabet.base());
+        Ok(Selection {
+            indices,
+            digits,
+            joltage,
+        })
+    }
+
+    /// The higher of two batteries, the leftmost one on ties
+    fn best(&self, a: Option<usize>, b: Option<usize>) -> Option<usize> {
+        match (a, b) {
+            (Some(a), Some(b)) => {
+                let key = |index: usize| (self.batteries[index].digit, std::cmp::Reverse(index));
+                Some(if key(a) >= key(b) { a } else { b })
+            }
+            _ => a.or(b),
+        }
+    }
+
+    /// Leftmost highest battery in `start..end`
+    fn query(&self, start: usize, end: usize) -> Option<usize> {
+        let (mut left, mut right) = (start + self.leaves, end + self.leaves);
+        let mut best = None;
+        while left < right {
+            if left % 2 == 1 {
+                best = self.best(best, self.tree[left]);
+                left += 1;
+            }
+            if right % 2 == 1 {
+                right -= 1;
+                best = self.best(best, self.tree[right]);
+            }
+            left /= 2;
+            right /= 2;
+        }
+        best
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Mode;
+    use crate::tests::Rng;
+
+    #[test]
+    fn test_max_joltage() {
+        let dynamic = DynamicBank::new(Bank::new("818181911112111").unwrap());
+        let selection = dynamic.max_joltage(12).unwrap();
+        assert_eq!(selection.joltage.to_string(), "888911112111");
+        assert_eq!(dynamic.max_joltage(0).unwrap().indices, Vec::<usize>::new());
+        assert!(dynamic.max_joltage(16).is_err());
+    }
+
+    #[test]
+    fn test_set_digit() {
+        let mut dynamic = DynamicBank::new(Bank::new("818181911112111").unwrap());
+        dynamic.set_digit(1, 9);
+        assert_eq!(dynamic.max_joltage(2).unwrap().joltage.to_string(), "99");
+        dynamic.set_digit(6, 0);
+        assert_eq!(dynamic.max_joltage(2).unwrap().indices, vec![1, 2]);
+        // ties go to the leftmost battery, like the scanning selection
+        dynamic.set_digit(0, 9);
+        assert_eq!(dynamic.max_joltage(1).unwrap().indices, vec![0]);
+    }
+
+    #[test]
+    fn test_other_bases() {
+        let hex = Alphabet::radix(16).unwrap();
+        let mut dynamic = DynamicBank::new(Bank::with_alphabet("1f3a0e", &hex).unwrap());
+        assert_eq!(hex.format(&dynamic.max_joltage(3).unwrap().joltage), "FAE");
+        dynamic.set_digit(4, 15);
+        assert_eq!(hex.format(&dynamic.max_joltage(3).unwrap().joltage), "FFE");
+    }
+
+    #[test]
+    #[should_panic(expected = "single base 10 digit, not 10")]
+    fn test_digit_outside_base() {
+        DynamicBank::new(Bank::new("123").unwrap()).set_digit(0, 10);
+    }
+
+    #[test]
+    fn test_matches_bank_after_updates() {
+        let mut rng = Rng(0xd1a4_0bad_0000_0048);
+        for _ in 0..50 {
+            let length = 1 + rng.below(40);
+            let mut digits: Vec<u8> = (0..length).map(|_| rng.below(10) as u8).collect();
+            let line = |digits: &[u8]| digits.iter().map(u8::to_string).collect::<String>();
+            let mut dynamic = DynamicBank::new(Bank::new(&line(&digits)).unwrap());
+            for _ in 0..50 {
+                let index = rng.below(length);
+                digits[index] = rng.below(10) as u8;
+                dynamic.set_digit(index, digits[index]);
+                let count = rng.below(length + 1);
+                let bank = Bank::new(&line(&digits)).unwrap();
+                assert_eq!(
+                    dynamic.max_joltage(count),
+                    bank.select(count, &Mode::Max),
+                    "{digits:?}, {count} batteries"
+                );
+            }
+        }
+    }
+
+    #[test]
+    #[ignore = "benchmark, run with `cargo test --release -- --ignored --nocapture`"]
+    fn bench_requery_after_update() {
+        use std::time::Instant;
+
+        const LENGTH: usize = 100_000;
+        const COUNT: usize = 12;
+        const UPDATES: usize = 1000;
+        let mut rng = Rng(0xbe4c_0000_0000_0048);
+        let line: String = (0..LENGTH)
+            .map(|_| char::from(b'1' + rng.below(9) as u8))
+            .collect();
+        let mut dynamic = DynamicBank::new(Bank::new(&line).unwrap());
+        let mut bank = Bank::new(&line).unwrap();
+        let updates: Vec<(usize, u8)> = (0..UPDATES)
+            .map(|_| (rng.below(LENGTH), rng.below(10) as u8))
+            .collect();
+
+        let start = Instant::now();
+        let mut dynamic_total = Joltage::default();
+        for &(index, digit) in &updates {
+            dynamic.set_digit(index, digit);
+            dynamic_total += &dynamic.max_joltage(COUNT).unwrap().joltage;
+        }
+        let dynamic_elapsed = start.elapsed();
+
+        let start = Instant::now();
+        let mut scanned_total = Joltage::default();
+        for &(index, digit) in &updates {
+            bank.batteries[index].digit = digit;
+            scanned_total += &bank.select(COUNT, &Mode::Max).unwrap().joltage;
+        }
+        let scanned_elapsed = start.elapsed();
+
+        assert_eq!(dynamic_total, scanned_total);
+        println!(
+            "{UPDATES} updates of a {LENGTH} battery bank, picking {COUNT}: segment tree {dynamic_elapsed:?}, full selection {scanned_elapsed:?} ({:.1}x)",
+            scanned_elapsed.as_secs_f64() / dynamic_elapsed.as_secs_f64()
+        );
+    }
+}
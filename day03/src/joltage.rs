@@ -0,0 +1,292 @@
+use std::cmp::Ordering;
+use std::ops::{Add, AddAssign};
+
+/// A non-negative integer of any size, so joltages of long selections never overflow
+///
+/// Stored as base 2^32 limbs, least significant first, without trailing zero limbs so every value
+/// has exactly one representation.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Joltage {
+    limbs: Vec<u32>,
+}
+
+impl Joltage {
+    /// The number written with decimal `digits`, most significant first
+    pub fn from_digits(digits: &[u8]) -> Self {
+        Joltage::from_digits_in(digits, 10)
+    }
+
+    /// The number written with `digits` in base `radix`, most significant first
+    pub fn from_digits_in(digits: &[u8], radix: u32) -> Self {
+        let mut joltage = Joltage::default();
+        for &digit in digits {
+            debug_assert!(
+                u32::from(digit) < radix,
+                "{digit} isn't a base {radix} digit"
+            );
+            joltage.mul_add(radix, u32::from(digit));
+        }
+        joltage
+    }
+
+    /// Digits of the number in base `radix`, most significant first, a single 0 for zero
+    pub fn digits_in(&self, radix: u32) -> Vec<u8> {
+        assert!(radix >= 2, "There's no base {radix}");
+        let mut rest = self.clone();
+        let mut digits = Vec::new();
+        loop {
+            digits.push(rest.div_rem(radix) as u8);
+            if rest.limbs.is_empty() {
+                break;
+            }
+        }
+        digits.reverse();
+        digits
+    }
+
+    /// The distance between two joltages
+    pub fn abs_diff(&self, other: &Joltage) -> Joltage {
+        let (mut larger, smaller) = match self.cmp(other) {
+            Ordering::Less => (other.clone(), self),
+            _ => (self.clone(), other),
+        };
+        let mut borrow = 0;
+        for (index, limb) in larger.limbs.iter_mut().enumerate() {
+            let subtrahend = u64::from(smaller.limbs.get(index).copied().unwrap_or(0)) + borrow;
+            let value = u64::from(*limb);
+            (*limb, borrow) = if value >= subtrahend {
+                ((value - subtrahend) as u32, 0)
+            } else {
+                ((value + (1 << 32) - subtrahend) as u32, 1)
+            };
+        }
+        larger.trim();
+        larger
+    }
+
+    /// `self = self * factor + term`
+    fn mul_add(&mut self, factor: u32, term: u32) {
+        let mut carry = u64::from(term);
+        for limb in &mut self.limbs {
+            let value = u64::from(*limb) * u64::from(factor) + carry;
+            *limb = value as u32;
+            carry = value >> 32;
+        }
+        if carry > 0 {
+            self.limbs.push(carry as u32);
+        }
+    }
+
+    /// `self = self / divisor`, returning the remainder
+    fn div_rem(&mut self, divisor: u32) -> u32 {
+        let mut remainder = 0u64;
+        for limb in self.limbs.iter_mut().rev() {
+            let value = (remainder << 32) | u64::from(*limb);
+            *limb = (value / u64::from(divisor)) as u32;
+            remainder = value % u64::from(divisor);
+        }
+        self.trim();
+        remainder as u32
+    }
+
+    fn trim(&mut self) {
+        while self.limbs.last() == Some(&0) {
+            self.limbs.pop();
+        }
+    }
+}
+
+impl std::str::FromStr for Joltage {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let digits = s
+            .chars()
+            .map(|c| c.to_digit(10).map(|digit| digit as u8))
+            .collect::<Option<Vec<u8>>>()
+            .filter(|digits| !digits.is_empty())
+            .ok_or_else(|| format!("Invalid joltage: {s:?}"))?;
+        Ok(Joltage::from_digits(&digits))
+    }
+}
+
+impl Ord for Joltage {
+    fn cmp(&self, other: &Self) -> Ordering {
+        // no trailing zero limbs, so more limbs always means a bigger number
+        self.limbs
+            .len()
+            .cmp(&other.limbs.len())
+            .then_with(|| self.limbs.iter().rev().cmp(other.limbs.iter().rev()))
+    }
+}
+
+impl PartialOrd for Joltage {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl AddAssign<&Joltage> for Joltage {
+    fn add_assign(&mut self, other: &Joltage) {
+        if self.limbs.len() < other.limbs.len() {
+            self.limbs.resize(other.limbs.len(), 0);
+        }
+        let mut carry = 0;
+        for (index, limb) in self.limbs.iter_mut().enumerate() {
+            let value =
+                u64::from(*limb) + u64::from(other.limbs.get(index).copied().unwrap_or(0)) + carry;
+            *limb = value as u32;
+            carry = value >> 32;
+        }
+        if carry > 0 {
+            self.limbs.push(carry as u32);
+        }
+    }
+}
+
+impl Add<&Joltage> for Joltage {
+    type Output = Joltage;
+
+    fn add(mut self, other: &Joltage) -> Joltage {
+        self += other;
+        self
+    }
+}
+
+impl<'a> std::iter::Sum<&'a Joltage> for Joltage {
+    fn sum<I: Iterator<Item = &'a Joltage>>(iter: I) -> Self {
+        iter.fold(Joltage::default(), Joltage::add)
+    }
+}
+
+impl std::fmt::Display for Joltage {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        // peel off 9 decimal digits at a time, least significant first
+        const CHUNK: u32 = 1_000_000_000;
+        let mut rest = self.clone();
+        let mut chunks = Vec::new();
+        loop {
+            chunks.push(rest.div_rem(CHUNK));
+            if rest.limbs.is_empty() {
+                break;
+            }
+        }
+        let mut chunks = chunks.iter().rev();
+        write!(f, "{}", chunks.next().unwrap())?;
+        for chunk in chunks {
+            write!(f, "{chunk:09}")?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::digits;
+
+    #[test]
+    fn test_from_digits() {
+        assert_eq!(Joltage::from_digits(&[]).to_string(), "0");
+        assert_eq!(Joltage::from_digits(&[0, 0]), Joltage::default());
+        assert_eq!(Joltage::from_digits(&[0, 4, 2]).to_string(), "42");
+        for number in [
+            "987654321111",
+            "4294967295",
+            "4294967296",
+            "18446744073709551616",
+            "1000000000000000000000000000000000000000000000000000000000000",
+        ] {
+            assert_eq!(Joltage::from_digits(&digits(number)).to_string(), number);
+        }
+    }
+
+    #[test]
+    fn test_radix() {
+        let joltage = Joltage::from_digits_in(&[15, 15, 0], 16);
+        assert_eq!(joltage.to_string(), "4080");
+        assert_eq!(joltage.digits_in(16), vec![15, 15, 0]);
+        assert_eq!(joltage.digits_in(10), vec![4, 0, 8, 0]);
+        assert_eq!(joltage.digits_in(2).len(), 12);
+        assert_eq!(
+            Joltage::from_digits_in(&[0, 0, 1], 36).digits_in(36),
+            vec![1]
+        );
+        assert_eq!(Joltage::default().digits_in(7), vec![0]);
+
+        // round trips beyond a single limb in every base
+        let big = Joltage::from_digits(&digits(
+            "1000000000000000000000000000000000000000000000000000000000000",
+        ));
+        for radix in 2..=36 {
+            assert_eq!(Joltage::from_digits_in(&big.digits_in(radix), radix), big);
+        }
+    }
+
+    #[test]
+    fn test_add() {
+        let max = Joltage::from_digits(&digits(&u128::MAX.to_string()));
+        let one = Joltage::from_digits(&[1]);
+        assert_eq!(
+            (max.clone() + &one).to_string(),
+            "340282366920938463463374607431768211456"
+        );
+        assert_eq!((one.clone() + &max), max.clone() + &one);
+        assert_eq!((Joltage::default() + &one), one);
+
+        let values = [u128::MAX / 3, 12345, u128::MAX / 7];
+        let joltages: Vec<Joltage> = values
+            .iter()
+            .map(|value| Joltage::from_digits(&digits(&value.to_string())))
+            .collect();
+        let total: Joltage = joltages.iter().sum();
+        assert_eq!(total.to_string(), values.iter().sum::<u128>().to_string());
+
+        let total: Joltage = [max.clone(), max.clone(), max].iter().sum();
+        assert_eq!(
+            total.to_string(),
+            "1020847100762815390390123822295304634365"
+        );
+    }
+
+    #[test]
+    fn test_abs_diff() {
+        let joltage = |number: &str| number.parse::<Joltage>().unwrap();
+        assert_eq!(
+            joltage("4294967296").abs_diff(&joltage("1")).to_string(),
+            "4294967295"
+        );
+        assert_eq!(
+            joltage("1").abs_diff(&joltage("4294967296")).to_string(),
+            "4294967295"
+        );
+        assert_eq!(
+            joltage("100000000000000000000000000000000000000000")
+                .abs_diff(&joltage("99999999999999999999999999999999999999999"))
+                .to_string(),
+            "1"
+        );
+        assert_eq!(joltage("42").abs_diff(&joltage("42")), Joltage::default());
+    }
+
+    #[test]
+    fn test_from_str() {
+        assert_eq!("0042".parse::<Joltage>().unwrap().to_string(), "42");
+        assert!("".parse::<Joltage>().is_err());
+        assert!("-1".parse::<Joltage>().is_err());
+        assert!("12a".parse::<Joltage>().is_err());
+    }
+
+    #[test]
+    fn test_ord() {
+        let joltages: Vec<Joltage> = ["0", "9", "10", "4294967296", "99999999999999999999"]
+            .iter()
+            .map(|number| Joltage::from_digits(&digits(number)))
+            .collect();
+        assert!(joltages.windows(2).all(|pair| pair[0] < pair[1]));
+        assert_eq!(
+            Joltage::from_digits(&digits("0042")).cmp(&Joltage::from_digits(&digits("42"))),
+            Ordering::Equal
+        );
+    }
+}
@@ -1,3 +1,21 @@
+mod alphabet;
+mod budget;
+mod constraints;
+mod dynamic;
+mod joltage;
+mod ranking;
+mod render;
+
+use alphabet::Alphabet;
+use budget::Budget;
+use constraints::Constraints;
+use dynamic::DynamicBank;
+use joltage::Joltage;
+use render::Format;
+use std::cmp::{Ordering, Reverse};
+use std::io::IsTerminal;
+use std::ops::Range;
+
 const MAX_BATTERIES_ENABLED: usize = 12;
 
 #[derive(Debug)]
@@ -16,98 +34,658 @@ impl Battery {
 
     fn enable(&mut self) {
         self.enabled = true;
-        
     }
 
     fn disable(&mut self) {
         self.enabled = false;
     }
+}
+
+/// The batteries picked for a joltage, without touching the bank they came from
+#[derive(Debug, PartialEq)]
+struct Selection {
+    /// Positions of the picked batteries in the bank, in increasing order
+    indices: Vec<usize>,
+    digits: Vec<u8>,
+    joltage: Joltage,
+}
+
+#[derive(Debug, PartialEq)]
+enum ParseError {
+    Empty,
+    InvalidDigit { position: usize, found: char },
+}
+
+impl std::fmt::Display for ParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            ParseError::Empty => write!(f, "empty bank"),
+            ParseError::InvalidDigit { position, found } => {
+                write!(f, "invalid battery {found:?} at position {position}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+/// Which joltage a selection aims for
+#[derive(Debug, Clone, PartialEq)]
+enum Mode {
+    Max,
+    /// The lowest joltage that doesn't start with a zero, unless every selection does
+    Min,
+    /// The joltage closest to the target, the lower one on ties
+    Nearest(Joltage),
+}
+
+/// What to select in every bank, from the command line
+#[derive(Debug, PartialEq)]
+struct Options {
+    mode: Mode,
+    /// Only supported for the maximum joltage
+    constraints: Constraints,
+    /// Spread a total over all banks instead of enabling the same number in each, only for the maximum joltage
+    budget: Option<Budget>,
+    /// List this many of the highest joltages of every bank, only for the unconstrained maximum joltage
+    top: Option<usize>,
+    /// Batteries to replace one after the other, reporting the maximum total joltage after each one
+    swaps: Vec<Swap>,
+    /// How to show the selected batteries, colored text on a terminal and plain text otherwise if not given
+    render: Option<Format>,
+    /// Batteries per line before wrapping
+    width: usize,
+    /// How the banks are written, joltages are written the same way unless `decimal` is set
+    alphabet: Alphabet,
+    decimal: bool,
+}
+
+/// Replace the battery at `position` in bank `bank` with one rated `digit`, in the base of the bank
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Swap {
+    bank: usize,
+    position: usize,
+    digit: u8,
+}
+
+impl Swap {
+    /// Parse `<bank>:<position>=<symbol>`, the symbol written like the batteries of the banks
+    fn parse(value: &str, alphabet: &Alphabet) -> Result<Self, String> {
+        let error = || format!("Invalid swap: {value:?}, expected <bank>:<position>=<symbol>");
+        let (bank, rest) = value.split_once(':').ok_or_else(error)?;
+        let (position, symbol) = rest.split_once('=').ok_or_else(error)?;
+        let mut symbols = symbol.chars();
+        let (Some(symbol), None) = (symbols.next(), symbols.next()) else {
+            return Err(error());
+        };
+        Ok(Swap {
+            bank: bank.parse().map_err(|_| error())?,
+            position: position.parse().map_err(|_| error())?,
+            digit: alphabet.digit(symbol).ok_or_else(|| {
+                format!(
+                    "Can't swap in {symbol:?}, it isn't a battery in base {}",
+                    alphabet.base()
+                )
+            })?,
+        })
+    }
+}
+
+const USAGE: &str = "Usage:
+  day03 [max | min | nearest <joltage>] [--gap <count>] [--disabled <position,...>] [--segments <length>:<max>]
+        [--render plain|ansi|html|svg] [--width <count>]
+  day03 ... [--radix <2-36> | --alphabet <symbols>] [--decimal]
+  day03 [max] --budget <total> [--min-per-bank <count>] [--max-per-bank <count>]
+  day03 [max] --top <count>
+  day03 [max] --swap <bank>:<position>=<symbol> [--swap ...]";
+
+impl Options {
+    /// Parse the command line arguments following the program name
+    fn parse(args: impl IntoIterator<Item = String>) -> Result<Self, String> {
+        let mut args = args.into_iter().peekable();
+        let mut target = None;
+        let mode = match args.peek().map(String::as_str) {
+            Some("max") => {
+                args.next();
+                Mode::Max
+            }
+            Some("min") => {
+                args.next();
+                Mode::Min
+            }
+            // the target is written like the banks, so it's only parsed once the alphabet is known
+            Some("nearest") => {
+                args.next();
+                target = Some(args.next().ok_or("nearest needs a target joltage")?);
+                Mode::Nearest(Joltage::default())
+            }
+            _ => Mode::Max,
+        };
+
+        let mut constraints = Constraints::default();
+        let mut budget = None;
+        let mut top = None;
+        let mut swaps = Vec::new();
+        let mut render = None;
+        let mut width = None;
+        let mut alphabet = None;
+        let mut decimal = false;
+        let (mut min_per_bank, mut max_per_bank) = (None, None);
+        let count = |value: &str| {
+            value
+                .parse::<usize>()
+                .map_err(|_| format!("Invalid battery count: {value:?}"))
+        };
+        while let Some(arg) = args.next() {
+            if arg == "--decimal" {
+                decimal = true;
+                continue;
+            }
+            let value = args
+                .next()
+                .ok_or_else(|| format!("Unknown argument or missing value: {arg:?}"))?;
+            match arg.as_str() {
+                "--gap" => {
+                    constraints.min_gap = value
+                        .parse()
+                        .map_err(|_| format!("Invalid gap: {value:?}"))?
+                }
+                "--disabled" => {
+                    constraints.disabled = value
+                        .split(',')
+                        .map(|position| position.trim().parse())
+                        .collect::<Result<_, _>>()
+                        .map_err(|_| format!("Invalid disabled positions: {value:?}"))?
+                }
+                "--segments" => {
+                    constraints.segment_cap = Some(Constraints::parse_segment_cap(&value)?)
+                }
+                "--budget" => budget = Some(count(&value)?),
+                "--min-per-bank" => min_per_bank = Some(count(&value)?),
+                "--max-per-bank" => max_per_bank = Some(count(&value)?),
+                "--top" => top = Some(count(&value)?),
+                "--swap" => swaps.push(value),
+                "--render" => render = Some(value.parse()?),
+                "--width" => {
+                    width = Some(
+                        value
+                            .parse()
+                            .ok()
+                            .filter(|&width| width > 0)
+                            .ok_or_else(|| format!("Invalid width: {value:?}"))?,
+                    )
+                }
+                "--radix" | "--alphabet" if alphabet.is_some() => {
+                    return Err("Only one of --radix and --alphabet can be given".to_string());
+                }
+                "--radix" => {
+                    let radix = value
+                        .parse()
+                        .map_err(|_| format!("Invalid radix: {value:?}"))?;
+                    alphabet = Some(Alphabet::radix(radix)?)
+                }
+                "--alphabet" => alphabet = Some(Alphabet::symbols(&value)?),
+                _ => return Err(format!("Unknown argument: {arg:?}")),
+            }
+        }
+        let alphabet = alphabet.unwrap_or_default();
+        let mode = match target {
+            Some(target) => Mode::Nearest(alphabet.parse(&target)?),
+            None => mode,
+        };
+        // like the target, swapped in batteries are written like the banks
+        let swaps = swaps
+            .iter()
+            .map(|swap| Swap::parse(swap, &alphabet))
+            .collect::<Result<Vec<_>, _>>()?;
+        if mode != Mode::Max && constraints != Constraints::default() {
+            return Err("Constraints are only supported for the maximum joltage".to_string());
+        }
+        let budget = match budget {
+            Some(total) => {
+                let budget = Budget {
+                    total,
+                    min_per_bank: min_per_bank.unwrap_or(0),
+                    max_per_bank: max_per_bank.unwrap_or(MAX_BATTERIES_ENABLED),
+                };
+                if mode != Mode::Max || constraints != Constraints::default() {
+                    return Err(
+                        "A budget only works with the unconstrained maximum joltage".to_string()
+                    );
+                }
+                if budget.min_per_bank > budget.max_per_bank {
+                    return Err("--min-per-bank can't be above --max-per-bank".to_string());
+                }
+                Some(budget)
+            }
+            None if min_per_bank.is_some() || max_per_bank.is_some() => {
+                return Err("Per bank limits need a --budget".to_string());
+            }
+            None => None,
+        };
+        if top.is_some()
+            && (mode != Mode::Max || constraints != Constraints::default() || budget.is_some())
+        {
+            return Err("--top only works with the unconstrained maximum joltage".to_string());
+        }
+        if !swaps.is_empty()
+            && (mode != Mode::Max
+                || constraints != Constraints::default()
+                || budget.is_some()
+                || top.is_some())
+        {
+            return Err("--swap only works with the unconstrained maximum joltage".to_string());
+        }
+        if (render.is_some() || width.is_some())
+            && (budget.is_some() || top.is_some() || !swaps.is_empty())
+        {
+            return Err("--render and --width only work when selecting in every bank".to_string());
+        }
+        Ok(Options {
+            mode,
+            constraints,
+            budget,
+            top,
+            swaps,
+            render,
+            width: width.unwrap_or(render::DEFAULT_WIDTH),
+            alphabet,
+            decimal,
+        })
+    }
 
-    fn get_value(&self) -> u8 {
-        if self.enabled { self.digit as u8 } else { 0 }
+    /// A joltage written like the banks, or in decimal if asked for
+    fn show(&self, joltage: &Joltage) -> String {
+        if self.decimal {
+            joltage.to_string()
+        } else {
+            self.alphabet.format(joltage)
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+enum SelectionError {
+    NotEnoughBatteries {
+        requested: usize,
+        available: usize,
+    },
+    /// No selection of the requested size satisfies the constraints
+    Infeasible {
+        requested: usize,
+    },
+}
+
+impl std::fmt::Display for SelectionError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            SelectionError::NotEnoughBatteries {
+                requested,
+                available,
+            } => write!(
+                f,
+                "can't enable {requested} batteries in a bank of {available}"
+            ),
+            SelectionError::Infeasible { requested } => write!(
+                f,
+                "no way to enable {requested} batteries within the constraints"
+            ),
+        }
     }
 }
 
+impl std::error::Error for SelectionError {}
+
 struct Bank {
     batteries: Vec<Battery>,
+    /// The ratings of the batteries, and the base of their joltage
+    alphabet: Alphabet,
 }
 
 impl Bank {
-    fn new(input: &str) -> Self {
+    /// Parse a line of decimal digits, one battery per digit
+    #[cfg(test)]
+    fn new(input: &str) -> Result<Self, ParseError> {
+        Bank::with_alphabet(input, &Alphabet::default())
+    }
+
+    /// Parse a line of symbols from `alphabet`, one battery per symbol
+    fn with_alphabet(input: &str, alphabet: &Alphabet) -> Result<Self, ParseError> {
+        // positions in errors count from the start of the line as written, not of the trimmed one
+        let leading = input.chars().take_while(|c| c.is_whitespace()).count();
+        let input = input.trim();
+        if input.is_empty() {
+            return Err(ParseError::Empty);
+        }
         let batteries = input
             .chars()
-            .filter_map(|c| c.to_digit(10))
-            .map(|d| Battery::new(d as u8))
-            .collect();
-        Bank { batteries }
-    }
-
-    /// From a bank of batteries, find the maximum joltage
-    fn max_joltage(&mut self) -> u64 {
-        let mut total_enabled_count = 0;
-        for i in (1..=9).rev() {
-            let mut curr_digit_enabled_count = 0;
-            // starting from the highest possible digit, enable all batteries with that digit
-            for battery in &mut self.batteries.iter_mut().rev() {
-                if battery.digit as usize == i && total_enabled_count < MAX_BATTERIES_ENABLED {
-                    curr_digit_enabled_count += 1;
-                    total_enabled_count += 1;
-                    battery.enable();
-                }
+            .enumerate()
+            .map(|(index, c)| {
+                alphabet
+                    .digit(c)
+                    .map(Battery::new)
+                    .ok_or(ParseError::InvalidDigit {
+                        position: leading + index,
+                        found: c,
+                    })
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(Bank {
+            batteries,
+            alphabet: alphabet.clone(),
+        })
+    }
+
+    /// Pick `count` batteries, keeping their order, with the joltage `mode` asks for
+    ///
+    /// Zeros are picked like any other digit, so a selection always has exactly `count` batteries.
+    fn select(&self, count: usize, mode: &Mode) -> Result<Selection, SelectionError> {
+        if count > self.batteries.len() {
+            return Err(SelectionError::NotEnoughBatteries {
+                requested: count,
+                available: self.batteries.len(),
+            });
+        }
+        let mut indices = Vec::with_capacity(count);
+        match mode {
+            Mode::Max => self.extend(&mut indices, count, Some),
+            Mode::Min if count > 0 => {
+                // a leading zero would make the joltage shorter, so only lead with one if there's no choice
+                let window = 0..self.batteries.len() - (count - 1);
+                let first = self.best_in(window, |digit| Some(Reverse((digit == 0, digit))));
+                indices.push(first.expect("Every battery has a rank"));
+                self.extend(&mut indices, count - 1, |digit| Some(Reverse(digit)));
             }
-            println!("Enabled {curr_digit_enabled_count} batteries of digit {i}");
+            Mode::Min => {}
+            Mode::Nearest(target) => indices = self.nearest(count, target),
         }
-        assert!(
-            total_enabled_count <= MAX_BATTERIES_ENABLED,
-            "Enabled too many batteries: {total_enabled_count} (max {MAX_BATTERIES_ENABLED})",
-        );
-        self.print_batteries();
-        // calculate the total joltage
-        let enabled_batteries_string = self
-            .batteries
-            .iter()
-            .filter(|b| b.enabled)
-            .map(|b| b.digit.to_string())
-            .collect::<String>();
-        assert!(
-            !enabled_batteries_string.is_empty(),
-            "No batteries enabled?",
-        );
-        let joltage = enabled_batteries_string.parse::<u64>().expect(&format!(
-            "Failed to parse joltage: '{enabled_batteries_string}'",
-        ));
-        joltage
-    }
-
-    fn print_batteries(&self) {
-        println!("Batteries status:");
-        // first print a line with the digits
-        for battery in &self.batteries {
-            print!("{}", battery.digit);
-        }
-        println!();
-        // then print a line with the enabled status
-        for battery in &self.batteries {
-            if battery.enabled {
-                print!("^");
-            } else {
-                print!(" ");
+        Ok(self.selection(indices))
+    }
+
+    /// Pick `count` batteries with the maximum joltage that respects `constraints`
+    fn select_constrained(
+        &self,
+        count: usize,
+        constraints: &Constraints,
+    ) -> Result<Selection, SelectionError> {
+        if count > self.batteries.len() {
+            return Err(SelectionError::NotEnoughBatteries {
+                requested: count,
+                available: self.batteries.len(),
+            });
+        }
+        constraints::max_selection(&self.digits(), count, constraints)
+            .map(|indices| self.selection(indices))
+            .ok_or(SelectionError::Infeasible { requested: count })
+    }
+
+    /// The selections with the `n` highest distinct joltages, highest first
+    ///
+    /// Each joltage is picked at the leftmost batteries that give it, see `count_max_selections` for how
+    /// many other ways there are to get the highest one.
+    fn top_selections(&self, count: usize, n: usize) -> Result<Vec<Selection>, SelectionError> {
+        if count > self.batteries.len() {
+            return Err(SelectionError::NotEnoughBatteries {
+                requested: count,
+                available: self.batteries.len(),
+            });
+        }
+        Ok(
+            ranking::top_selections(&self.digits(), self.alphabet.base(), count, n)
+                .into_iter()
+                .map(|indices| self.selection(indices))
+                .collect(),
+        )
+    }
+
+    /// Number of different sets of `count` batteries that give the maximum joltage, saturating at `u128::MAX`
+    fn count_max_selections(&self, count: usize) -> Result<u128, SelectionError> {
+        let best = self.select(count, &Mode::Max)?;
+        Ok(ranking::count_selections(&self.digits(), &best.digits))
+    }
+
+    fn digits(&self) -> Vec<u8> {
+        self.batteries.iter().map(|battery| battery.digit).collect()
+    }
+
+    /// Add `count` batteries after the last picked one, greedily picking the best `rank` at each step
+    ///
+    /// Each next battery is the best one that still leaves enough batteries after it for the rest of
+    /// the selection, taking the leftmost one on ties so the later picks have the most to choose from.
+    fn extend<K: Ord>(
+        &self,
+        indices: &mut Vec<usize>,
+        count: usize,
+        rank: impl Fn(u8) -> Option<K>,
+    ) {
+        for remaining in (0..count).rev() {
+            let start = indices.last().map_or(0, |&index| index + 1);
+            let best = self
+                .best_in(start..self.batteries.len() - remaining, &rank)
+                .expect("Every battery has a rank");
+            indices.push(best);
+        }
+    }
+
+    /// Leftmost battery in `window` with the highest rank, skipping the ones without a rank
+    fn best_in<K: Ord>(
+        &self,
+        window: Range<usize>,
+        rank: impl Fn(u8) -> Option<K>,
+    ) -> Option<usize> {
+        // max_by returns the last maximum, so search from the right to get the leftmost one
+        window
+            .rev()
+            .filter_map(|index| Some((rank(self.batteries[index].digit)?, index)))
+            .max_by(|(a, _), (b, _)| a.cmp(b))
+            .map(|(_, index)| index)
+    }
+
+    /// Indices of the selection closest to `target`, preferring the lower one on ties
+    fn nearest(&self, count: usize, target: &Joltage) -> Vec<usize> {
+        let mut target_digits = target.digits_in(self.alphabet.base());
+        if target_digits.len() > count {
+            // every selection is below the target, so the highest one is the closest
+            let mut indices = Vec::with_capacity(count);
+            self.extend(&mut indices, count, Some);
+            return indices;
+        }
+        let padding = count - target_digits.len();
+        target_digits.splice(0..0, std::iter::repeat_n(0, padding));
+
+        [
+            self.closest(&target_digits, Ordering::Less),
+            self.closest(&target_digits, Ordering::Greater),
+        ]
+        .into_iter()
+        .flatten()
+        .min_by_key(|indices| self.selection(indices.clone()).joltage.abs_diff(target))
+        .expect("There's always a selection")
+    }
+
+    /// Indices of the closest selection on the `side` of `target`, or exactly `target` if it can be picked
+    ///
+    /// The closest selection follows the target for as many digits as possible, then diverges with the
+    /// closest digit on the right side and fills up with the extreme digits towards the target.
+    fn closest(&self, target: &[u8], side: Ordering) -> Option<Vec<usize>> {
+        let count = target.len();
+        // the earliest batteries spelling out the longest possible prefix of the target
+        let mut prefix = Vec::with_capacity(count);
+        for &digit in target {
+            let start = prefix.last().map_or(0, |&index| index + 1);
+            let window = start..self.batteries.len() - (count - prefix.len() - 1);
+            match self.best_in(window, |candidate| (candidate == digit).then_some(())) {
+                Some(index) => prefix.push(index),
+                None => break,
             }
         }
-        println!();
+        if prefix.len() == count {
+            return Some(prefix);
+        }
+
+        (0..=prefix.len()).rev().find_map(|matched| {
+            let mut indices = prefix[..matched].to_vec();
+            let start = indices.last().map_or(0, |&index| index + 1);
+            let window = start..self.batteries.len() - (count - matched - 1);
+            let remaining = count - matched - 1;
+            let wanted = target[matched];
+            match side {
+                Ordering::Less => {
+                    indices.push(self.best_in(window, |digit| (digit < wanted).then_some(digit))?);
+                    self.extend(&mut indices, remaining, Some);
+                }
+                _ => {
+                    indices.push(
+                        self.best_in(window, |digit| (digit > wanted).then_some(Reverse(digit)))?,
+                    );
+                    self.extend(&mut indices, remaining, |digit| Some(Reverse(digit)));
+                }
+            }
+            Some(indices)
+        })
+    }
+
+    fn selection(&self, indices: Vec<usize>) -> Selection {
+        let digits: Vec<u8> = indices
+            .iter()
+            .map(|&index| self.batteries[index].digit)
+            .collect();
+        let joltage = Joltage::from_digits_in(&digits, self.alphabet.base());
+        Selection {
+            indices,
+            digits,
+            joltage,
+        }
+    }
+
+    /// Enable exactly the batteries in `selection`, disabling every other one
+    fn apply(&mut self, selection: &Selection) {
+        for battery in &mut self.batteries {
+            battery.disable();
+        }
+        for &index in &selection.indices {
+            self.batteries[index].enable();
+        }
     }
 }
 
 fn main() {
+    let options = Options::parse(std::env::args().skip(1)).unwrap_or_else(|e| {
+        eprintln!("{e}\n{USAGE}");
+        std::process::exit(2);
+    });
     let input = include_str!("../data/input.txt");
 
-    let mut max_joltage = 0;
+    if let Some(budget) = options.budget {
+        let banks: Vec<Bank> = input
+            .lines()
+            .map(|line| {
+                Bank::with_alphabet(line, &options.alphabet)
+                    .unwrap_or_else(|e| panic!("Invalid bank '{line}': {e}"))
+            })
+            .collect();
+        let allocation =
+            budget::allocate(&banks, &budget).unwrap_or_else(|e| panic!("Invalid budget: {e}"));
+        for (index, selection) in allocation.selections.iter().enumerate() {
+            println!(
+                "Bank {index}: {} batteries, joltage {}",
+                selection.indices.len(),
+                options.show(&selection.joltage)
+            );
+        }
+        println!("Total joltage: {}", options.show(&allocation.total));
+        return;
+    }
+
+    if !options.swaps.is_empty() {
+        let mut banks: Vec<DynamicBank> = input
+            .lines()
+            .map(|line| {
+                DynamicBank::new(
+                    Bank::with_alphabet(line, &options.alphabet)
+                        .unwrap_or_else(|e| panic!("Invalid bank '{line}': {e}")),
+                )
+            })
+            .collect();
+        let max_joltage = |bank: &DynamicBank| {
+            bank.max_joltage(MAX_BATTERIES_ENABLED)
+                .unwrap_or_else(|e| panic!("Invalid bank: {e}"))
+                .joltage
+        };
+        // only the bank that had a battery swapped needs a new query
+        let mut joltages: Vec<Joltage> = banks.iter().map(max_joltage).collect();
+        println!(
+            "Total joltage: {}",
+            options.show(&joltages.iter().sum::<Joltage>())
+        );
+        for swap in &options.swaps {
+            let Some(bank) = banks.get_mut(swap.bank) else {
+                panic!("There's no bank {}", swap.bank);
+            };
+            if swap.position >= bank.len() {
+                panic!("Bank {} has no battery {}", swap.bank, swap.position);
+            }
+            bank.set_digit(swap.position, swap.digit);
+            joltages[swap.bank] = max_joltage(bank);
+            println!(
+                "After swapping in a {} at {}:{}: bank joltage {}, total joltage {}",
+                options.alphabet.symbol(swap.digit),
+                swap.bank,
+                swap.position,
+                options.show(&joltages[swap.bank]),
+                options.show(&joltages.iter().sum::<Joltage>())
+            );
+        }
+        return;
+    }
+
+    if let Some(n) = options.top {
+        for (index, line) in input.lines().enumerate() {
+            let bank = Bank::with_alphabet(line, &options.alphabet)
+                .unwrap_or_else(|e| panic!("Invalid bank '{line}': {e}"));
+            let (top, ties) = bank
+                .top_selections(MAX_BATTERIES_ENABLED, n)
+                .and_then(|top| Ok((top, bank.count_max_selections(MAX_BATTERIES_ENABLED)?)))
+                .unwrap_or_else(|e| panic!("Invalid bank '{line}': {e}"));
+            println!("Bank {index}: {ties} selection(s) give the highest joltage");
+            for (rank, selection) in top.iter().enumerate() {
+                println!(
+                    "  {}. {} at {:?}",
+                    rank + 1,
+                    options.show(&selection.joltage),
+                    selection.indices
+                );
+            }
+        }
+        return;
+    }
+
+    let mut banks = Vec::new();
     for line in input.lines() {
-        let mut bank = Bank::new(line);
-        let joltage = bank.max_joltage();
-        max_joltage += joltage;
+        let mut bank = Bank::with_alphabet(line, &options.alphabet)
+            .unwrap_or_else(|e| panic!("Invalid bank '{line}': {e}"));
+        let selection = if options.constraints == Constraints::default() {
+            bank.select(MAX_BATTERIES_ENABLED, &options.mode)
+        } else {
+            bank.select_constrained(MAX_BATTERIES_ENABLED, &options.constraints)
+        }
+        .unwrap_or_else(|e| panic!("Invalid bank '{line}': {e}"));
+        bank.apply(&selection);
+        banks.push(bank);
     }
-    println!("Total max joltage: {}", max_joltage);
+    let format = options.render.unwrap_or_else(|| {
+        // https://no-color.org
+        if std::io::stdout().is_terminal() && std::env::var_os("NO_COLOR").is_none() {
+            Format::Ansi
+        } else {
+            Format::Plain
+        }
+    });
+    print!(
+        "{}",
+        render::report(&banks, format, options.width, |joltage| options
+            .show(joltage))
+    );
 }
 
 #[cfg(test)]
@@ -118,15 +696,594 @@ mod tests {
     fn test_example() {
         let example_input = include_str!("../data/example.txt");
 
-        let mut max_joltage: u64 = 0;
+        let mut max_joltage = Joltage::default();
         for line in example_input.lines() {
-            let mut bank = Bank::new(line);
-            let joltage = bank.max_joltage();
+            let bank = Bank::new(line).unwrap();
+            let joltage = bank
+                .select(MAX_BATTERIES_ENABLED, &Mode::Max)
+                .unwrap()
+                .joltage;
             println!("Joltage: {}", joltage);
-            max_joltage += joltage;
+            max_joltage += &joltage;
             println!("Accumulated joltage: {}", max_joltage);
         }
         println!("Total max joltage: {}", max_joltage);
-        assert_eq!(max_joltage, 3121910778619);
+        assert_eq!(max_joltage.to_string(), "3121910778619");
+    }
+
+    #[test]
+    fn test_selection() {
+        let bank = Bank::new("818181911112111").unwrap();
+        assert_eq!(
+            bank.select(2, &Mode::Max).unwrap(),
+            Selection {
+                indices: vec![6, 11],
+                digits: vec![9, 2],
+                joltage: Joltage::from_digits(&[9, 2])
+            }
+        );
+        let selection = bank.select(12, &Mode::Max).unwrap();
+        assert_eq!(
+            selection.indices,
+            vec![0, 2, 4, 6, 7, 8, 9, 10, 11, 12, 13, 14]
+        );
+        assert_eq!(selection.joltage.to_string(), "888911112111");
+        assert_eq!(bank.select(0, &Mode::Max).unwrap().joltage.to_string(), "0");
+        assert_eq!(
+            bank.select(15, &Mode::Max).unwrap().joltage.to_string(),
+            "818181911112111"
+        );
+    }
+
+    #[test]
+    fn test_queries_are_independent() {
+        let mut bank = Bank::new("234234234234278").unwrap();
+        let twelve = bank.select(12, &Mode::Max).unwrap();
+        bank.apply(&twelve);
+        assert_eq!(bank.batteries.iter().filter(|b| b.enabled).count(), 12);
+
+        // neither asking again nor applying a smaller selection is affected by the previous one
+        assert_eq!(bank.select(12, &Mode::Max).unwrap(), twelve);
+        let two = bank.select(2, &Mode::Max).unwrap();
+        assert_eq!(two.joltage.to_string(), "78");
+        bank.apply(&two);
+        let enabled: Vec<usize> = (0..bank.batteries.len())
+            .filter(|&index| bank.batteries[index].enabled)
+            .collect();
+        assert_eq!(enabled, vec![13, 14]);
+    }
+
+    #[test]
+    fn test_joltage_beyond_u64() {
+        let line = include_str!("../data/input.txt").lines().next().unwrap();
+        let bank = Bank::new(line).unwrap();
+        // enabling every battery gives the bank itself as a number
+        assert_eq!(
+            bank.select(line.len(), &Mode::Max)
+                .unwrap()
+                .joltage
+                .to_string(),
+            line.trim_start_matches('0')
+        );
+        let selection = bank.select(40, &Mode::Max).unwrap();
+        let expected: String = selection.digits.iter().map(u8::to_string).collect();
+        assert_eq!(selection.joltage.to_string(), expected);
+
+        let total: Joltage = [&selection.joltage, &selection.joltage].into_iter().sum();
+        assert_eq!(total, selection.joltage.clone() + &selection.joltage);
+    }
+
+    #[test]
+    fn test_zeros() {
+        let bank = Bank::new("0000").unwrap();
+        let selection = bank.select(3, &Mode::Max).unwrap();
+        assert_eq!(selection.indices, vec![0, 1, 2]);
+        assert_eq!(selection.digits, vec![0, 0, 0]);
+        assert_eq!(selection.joltage.to_string(), "0");
+
+        let bank = Bank::new("1020304").unwrap();
+        assert_eq!(bank.select(3, &Mode::Max).unwrap().digits, vec![3, 0, 4]);
+        // the zeros are needed to make up the count
+        assert_eq!(
+            bank.select(5, &Mode::Max).unwrap().digits,
+            vec![2, 0, 3, 0, 4]
+        );
+        assert_eq!(
+            bank.select(7, &Mode::Max).unwrap().joltage.to_string(),
+            "1020304"
+        );
+    }
+
+    #[test]
+    fn test_not_enough_batteries() {
+        let bank = Bank::new("9876").unwrap();
+        assert_eq!(
+            bank.select(5, &Mode::Max),
+            Err(SelectionError::NotEnoughBatteries {
+                requested: 5,
+                available: 4
+            })
+        );
+        assert_eq!(
+            bank.select(5, &Mode::Max).unwrap_err().to_string(),
+            "can't enable 5 batteries in a bank of 4"
+        );
+        assert!(bank.select(4, &Mode::Max).is_ok());
+    }
+
+    /// Ratings of a decimal bank
+    pub(crate) fn digits(bank: &str) -> Vec<u8> {
+        bank.bytes().map(|b| b - b'0').collect()
+    }
+
+    /// Indices of every way to pick `count` of `length` batteries, in increasing order
+    pub(crate) fn all_picks(length: usize, count: usize) -> Vec<Vec<usize>> {
+        (0u32..1 << length)
+            .filter(|mask| mask.count_ones() as usize == count)
+            .map(|mask| {
+                (0..length)
+                    .filter(|&index| mask & (1 << index) != 0)
+                    .collect()
+            })
+            .collect()
+    }
+
+    /// Digits of every way to pick `count` batteries, keeping their order
+    pub(crate) fn all_selections(digits: &[u8], count: usize) -> Vec<Vec<u8>> {
+        all_picks(digits.len(), count)
+            .into_iter()
+            .map(|indices| indices.into_iter().map(|index| digits[index]).collect())
+            .collect()
+    }
+
+    fn value(digits: &[u8]) -> u64 {
+        digits
+            .iter()
+            .fold(0, |value, &digit| value * 10 + u64::from(digit))
+    }
+
+    fn brute_force_max_joltage(digits: &[u8], count: usize) -> u64 {
+        all_selections(digits, count)
+            .iter()
+            .map(|selection| value(selection))
+            .max()
+            .unwrap()
+    }
+
+    fn brute_force_min_joltage(digits: &[u8], count: usize) -> u64 {
+        all_selections(digits, count)
+            .iter()
+            .min_by_key(|selection| (selection.first() == Some(&0), value(selection)))
+            .map(|selection| value(selection))
+            .unwrap()
+    }
+
+    fn brute_force_nearest_joltage(digits: &[u8], count: usize, target: u64) -> u64 {
+        all_selections(digits, count)
+            .iter()
+            .map(|selection| value(selection))
+            .min_by_key(|&value| (value.abs_diff(target), value))
+            .unwrap()
+    }
+
+    /// A random bank of up to 12 batteries, and a random number of batteries to pick from it
+    fn random_bank(rng: &mut Rng) -> (Vec<u8>, Bank, usize) {
+        let length = 1 + rng.below(12);
+        // extra zeros on top of every digit, as they're as good a pick as any other digit
+        let digits: Vec<u8> = (0..length)
+            .map(|_| {
+                if rng.below(3) == 0 {
+                    0
+                } else {
+                    rng.below(10) as u8
+                }
+            })
+            .collect();
+        let bank = Bank {
+            batteries: digits.iter().map(|&digit| Battery::new(digit)).collect(),
+            alphabet: Alphabet::default(),
+        };
+        let count = rng.below(length + 1);
+        (digits, bank, count)
+    }
+
+    /// Check the selection is consistent with itself, and return its joltage
+    fn checked_joltage(bank: &Bank, selection: &Selection, count: usize) -> u64 {
+        assert_eq!(selection.indices.len(), count);
+        assert!(selection.indices.windows(2).all(|pair| pair[0] < pair[1]));
+        let digits: Vec<u8> = selection
+            .indices
+            .iter()
+            .map(|&index| bank.batteries[index].digit)
+            .collect();
+        assert_eq!(selection.digits, digits);
+        assert_eq!(selection.joltage.to_string(), value(&digits).to_string());
+        value(&digits)
+    }
+
+    #[test]
+    fn test_max_joltage_matches_brute_force() {
+        let mut rng = Rng(0x0da7_03b4_7735_0001);
+        for _ in 0..500 {
+            let (digits, bank, count) = random_bank(&mut rng);
+            let selection = bank.select(count, &Mode::Max).unwrap();
+            assert_eq!(
+                checked_joltage(&bank, &selection, count),
+                brute_force_max_joltage(&digits, count),
+                "{digits:?}, {count} batteries"
+            );
+        }
+    }
+
+    #[test]
+    fn test_min_joltage() {
+        let bank = Bank::new("818181911112111").unwrap();
+        assert_eq!(
+            bank.select(4, &Mode::Min).unwrap().joltage.to_string(),
+            "1111"
+        );
+        // no leading zero, even though "0012" is a lower number
+        let bank = Bank::new("3001200").unwrap();
+        assert_eq!(bank.select(4, &Mode::Min).unwrap().digits, vec![1, 2, 0, 0]);
+        assert_eq!(
+            bank.select(6, &Mode::Min).unwrap().digits,
+            vec![3, 0, 0, 1, 0, 0]
+        );
+        // unless every selection starts with one
+        let bank = Bank::new("0012").unwrap();
+        assert_eq!(bank.select(3, &Mode::Min).unwrap().digits, vec![0, 0, 1]);
+        assert_eq!(bank.select(0, &Mode::Min).unwrap().digits, vec![]);
+    }
+
+    #[test]
+    fn test_min_joltage_matches_brute_force() {
+        let mut rng = Rng(0x0da7_03b4_7735_0002);
+        for _ in 0..500 {
+            let (digits, bank, count) = random_bank(&mut rng);
+            let selection = bank.select(count, &Mode::Min).unwrap();
+            assert_eq!(
+                checked_joltage(&bank, &selection, count),
+                brute_force_min_joltage(&digits, count),
+                "{digits:?}, {count} batteries"
+            );
+        }
+    }
+
+    #[test]
+    fn test_nearest_joltage() {
+        let bank = Bank::new("987654321111111").unwrap();
+        let nearest = |count: usize, target: &str| {
+            let mode = Mode::Nearest(target.parse().unwrap());
+            bank.select(count, &mode).unwrap().joltage.to_string()
+        };
+        assert_eq!(nearest(3, "321"), "321");
+        assert_eq!(nearest(3, "500"), "511");
+        assert_eq!(nearest(3, "990"), "987");
+        assert_eq!(nearest(2, "12345"), "98");
+        assert_eq!(nearest(2, "0"), "11");
+        // 965 and 975 are both 5 away, the lower one wins
+        assert_eq!(
+            Bank::new("9765")
+                .unwrap()
+                .select(3, &Mode::Nearest("970".parse().unwrap()))
+                .unwrap()
+                .joltage
+                .to_string(),
+            "965"
+        );
+    }
+
+    #[test]
+    fn test_nearest_joltage_matches_brute_force() {
+        let mut rng = Rng(0x0da7_03b4_7735_0003);
+        for _ in 0..2000 {
+            let (digits, bank, count) = random_bank(&mut rng);
+            // targets of every length, including ones longer than any selection
+            let target = rng.next_u64() % 10u64.pow(rng.below(14) as u32);
+            let mode = Mode::Nearest(target.to_string().parse().unwrap());
+            let selection = bank.select(count, &mode).unwrap();
+            assert_eq!(
+                checked_joltage(&bank, &selection, count),
+                brute_force_nearest_joltage(&digits, count, target),
+                "{digits:?}, {count} batteries, target {target}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_parse_options() {
+        let parse = |args: &[&str]| Options::parse(args.iter().map(|arg| arg.to_string()));
+        let mode = |args: &[&str]| parse(args).map(|options| options.mode);
+        assert_eq!(mode(&[]), Ok(Mode::Max));
+        assert_eq!(mode(&["min"]), Ok(Mode::Min));
+        assert_eq!(
+            mode(&["nearest", "1234"]),
+            Ok(Mode::Nearest("1234".parse().unwrap()))
+        );
+        assert!(parse(&["nearest"]).is_err());
+        assert!(parse(&["nearest", "-5"]).is_err());
+        assert!(parse(&["max", "12"]).is_err());
+        assert!(parse(&["median"]).is_err());
+
+        assert_eq!(
+            parse(&["--gap", "1", "--disabled", "3, 5", "--segments", "10:4"]),
+            Ok(Options {
+                mode: Mode::Max,
+                constraints: Constraints {
+                    min_gap: 1,
+                    disabled: vec![3, 5],
+                    segment_cap: Some(constraints::SegmentCap { length: 10, max: 4 })
+                },
+                budget: None,
+                top: None,
+                swaps: vec![],
+                render: None,
+                width: render::DEFAULT_WIDTH,
+                alphabet: Alphabet::default(),
+                decimal: false
+            })
+        );
+        assert!(parse(&["--gap"]).is_err());
+        assert!(parse(&["--disabled", "1,x"]).is_err());
+        assert!(parse(&["min", "--gap", "1"]).is_err());
+
+        assert_eq!(
+            parse(&["--budget", "2400", "--min-per-bank", "1"]).map(|options| options.budget),
+            Ok(Some(Budget {
+                total: 2400,
+                min_per_bank: 1,
+                max_per_bank: MAX_BATTERIES_ENABLED
+            }))
+        );
+        assert!(
+            parse(&[
+                "--budget",
+                "10",
+                "--min-per-bank",
+                "3",
+                "--max-per-bank",
+                "2"
+            ])
+            .is_err()
+        );
+        assert!(parse(&["--max-per-bank", "2"]).is_err());
+        assert!(parse(&["min", "--budget", "10"]).is_err());
+        assert!(parse(&["--budget", "10", "--gap", "1"]).is_err());
+
+        assert_eq!(
+            parse(&["--top", "3"]).map(|options| options.top),
+            Ok(Some(3))
+        );
+        assert!(parse(&["min", "--top", "3"]).is_err());
+        assert!(parse(&["--top", "3", "--budget", "10"]).is_err());
+
+        assert_eq!(
+            parse(&["--swap", "3:17=9", "--swap", "0:0=0"]).map(|options| options.swaps),
+            Ok(vec![
+                Swap {
+                    bank: 3,
+                    position: 17,
+                    digit: 9
+                },
+                Swap {
+                    bank: 0,
+                    position: 0,
+                    digit: 0
+                }
+            ])
+        );
+        assert!(parse(&["--swap", "3:17=10"]).is_err());
+        assert!(parse(&["--swap", "3:17="]).is_err());
+        assert!(parse(&["--swap", "3=9"]).is_err());
+        assert!(parse(&["--swap", "3:x=9"]).is_err());
+        assert!(parse(&["--swap", "3:1=9", "--top", "2"]).is_err());
+
+        assert_eq!(
+            parse(&["min", "--render", "html", "--width", "20"])
+                .map(|options| (options.render, options.width)),
+            Ok((Some(Format::Html), 20))
+        );
+        assert!(parse(&["--render", "pdf"]).is_err());
+        assert!(parse(&["--width", "0"]).is_err());
+        assert!(parse(&["--width", "20", "--top", "2"]).is_err());
+
+        let options = parse(&["nearest", "ff", "--radix", "16", "--decimal"]).unwrap();
+        assert_eq!(options.mode, Mode::Nearest("255".parse().unwrap()));
+        assert_eq!(options.alphabet, Alphabet::Radix(16));
+        assert!(options.decimal);
+        assert_eq!(options.show(&"255".parse().unwrap()), "255");
+        let options = parse(&["--alphabet", "-+*"]).unwrap();
+        assert_eq!(options.show(&"5".parse().unwrap()), "+*");
+        assert!(parse(&["nearest", "ff"]).is_err());
+        assert!(parse(&["--radix", "37"]).is_err());
+        assert!(parse(&["--alphabet", "aa"]).is_err());
+        assert!(parse(&["--radix", "16", "--alphabet", "ab"]).is_err());
+        let swapped = |args: &[&str]| {
+            parse(args).map(|options| options.swaps.iter().map(|swap| swap.digit).collect())
+        };
+        assert_eq!(
+            swapped(&["--swap", "0:0=f", "--radix", "16", "--swap", "0:1=F"]),
+            Ok(vec![15, 15])
+        );
+        assert_eq!(
+            swapped(&["--alphabet", "-+*", "--swap", "0:0=*"]),
+            Ok(vec![2])
+        );
+        assert!(parse(&["--swap", "0:0=f"]).is_err());
+        assert!(parse(&["--radix", "16", "--swap", "0:0=15"]).is_err());
+        assert!(parse(&["--alphabet", "-+*", "--swap", "0:0=2"]).is_err());
+    }
+
+    #[test]
+    fn test_top_selections() {
+        let bank = Bank::new("234234234234278").unwrap();
+        let top = bank.top_selections(12, 3).unwrap();
+        let joltages: Vec<String> = top
+            .iter()
+            .map(|selection| selection.joltage.to_string())
+            .collect();
+        // 434234234278 can only be spelled one way, the runner up has to drop the first 3 instead
+        assert_eq!(
+            joltages,
+            vec!["434234234278", "424234234278", "423434234278"]
+        );
+        assert_eq!(top[0], bank.select(12, &Mode::Max).unwrap());
+        assert_eq!(bank.count_max_selections(12), Ok(1));
+
+        let bank = Bank::new("991999").unwrap();
+        // any two of the five nines
+        assert_eq!(bank.count_max_selections(2), Ok(10));
+        assert_eq!(bank.count_max_selections(5), Ok(1));
+        assert_eq!(bank.top_selections(2, 5).unwrap().len(), 3);
+        assert_eq!(
+            bank.top_selections(7, 1),
+            Err(SelectionError::NotEnoughBatteries {
+                requested: 7,
+                available: 6
+            })
+        );
+    }
+
+    #[test]
+    fn test_select_constrained() {
+        let bank = Bank::new("987654321111111").unwrap();
+        let constraints = Constraints {
+            min_gap: 1,
+            disabled: vec![0],
+            ..Constraints::default()
+        };
+        let selection = bank.select_constrained(3, &constraints).unwrap();
+        assert_eq!(selection.indices, vec![1, 3, 5]);
+        assert_eq!(selection.joltage.to_string(), "864");
+        assert_eq!(
+            bank.select_constrained(8, &constraints),
+            Err(SelectionError::Infeasible { requested: 8 })
+        );
+        assert_eq!(
+            bank.select_constrained(16, &constraints),
+            Err(SelectionError::NotEnoughBatteries {
+                requested: 16,
+                available: 15
+            })
+        );
+        // without constraints it's the same as the greedy selection
+        assert_eq!(
+            bank.select_constrained(12, &Constraints::default()),
+            bank.select(12, &Mode::Max)
+        );
+    }
+
+    /// Xorshift generator for the random banks, shared with the tests of the submodules so every seed
+    /// replays the same banks
+    pub(crate) struct Rng(pub(crate) u64);
+
+    impl Rng {
+        pub(crate) fn next_u64(&mut self) -> u64 {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 7;
+            self.0 ^= self.0 << 17;
+            self.0
+        }
+
+        /// Random index in [0, len)
+        pub(crate) fn below(&mut self, len: usize) -> usize {
+            (self.next_u64() % len as u64) as usize
+        }
+    }
+
+    #[test]
+    fn test_other_bases() {
+        let hex = Alphabet::radix(16).unwrap();
+        let bank = Bank::with_alphabet("1F3a0E", &hex).unwrap();
+        let selection = bank.select(3, &Mode::Max).unwrap();
+        assert_eq!(selection.digits, vec![15, 10, 14]);
+        assert_eq!(selection.joltage.to_string(), (0xfae).to_string());
+        assert_eq!(hex.format(&selection.joltage), "FAE");
+        let nearest = Mode::Nearest(hex.parse("3f").unwrap());
+        assert_eq!(hex.format(&bank.select(2, &nearest).unwrap().joltage), "3E");
+        assert_eq!(
+            Bank::with_alphabet("12g4", &hex).err(),
+            Some(ParseError::InvalidDigit {
+                position: 2,
+                found: 'g'
+            })
+        );
+
+        let alphabet = Alphabet::symbols("-+*").unwrap();
+        let bank = Bank::with_alphabet("+-*-+", &alphabet).unwrap();
+        let selection = bank.select(2, &Mode::Max).unwrap();
+        assert_eq!(alphabet.format(&selection.joltage), "*+");
+        assert_eq!(selection.joltage.to_string(), (2 * 3 + 1).to_string());
+        assert!(Bank::with_alphabet("+-0", &alphabet).is_err());
+    }
+
+    #[test]
+    fn test_other_bases_match_brute_force() {
+        let mut rng = Rng(0x0b45_e536_0000_0050);
+        for _ in 0..500 {
+            let radix = 2 + rng.below(35) as u32;
+            let alphabet = Alphabet::radix(radix).unwrap();
+            let length = 1 + rng.below(12);
+            let digits: Vec<u8> = (0..length)
+                .map(|_| rng.below(radix as usize) as u8)
+                .collect();
+            let line: String = digits.iter().map(|&digit| alphabet.symbol(digit)).collect();
+            let bank = Bank::with_alphabet(&line, &alphabet).unwrap();
+            let count = rng.below(length + 1);
+            let value = |digits: &[u8]| {
+                digits.iter().fold(0u64, |value, &digit| {
+                    value * u64::from(radix) + u64::from(digit)
+                })
+            };
+            let expected = all_selections(&digits, count)
+                .iter()
+                .map(|selection| value(selection))
+                .max()
+                .unwrap();
+            assert_eq!(
+                bank.select(count, &Mode::Max).unwrap().joltage.to_string(),
+                expected.to_string(),
+                "{line} in base {radix}, {count} batteries"
+            );
+        }
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert_eq!(Bank::new("  ").err(), Some(ParseError::Empty));
+        assert_eq!(
+            Bank::new("12a4").err(),
+            Some(ParseError::InvalidDigit {
+                position: 2,
+                found: 'a'
+            })
+        );
+        assert_eq!(Bank::new("9876\r").unwrap().batteries.len(), 4);
+        assert_eq!(
+            Bank::new(" \t12a4").err(),
+            Some(ParseError::InvalidDigit {
+                position: 4,
+                found: 'a'
+            })
+        );
+    }
+
+    #[test]
+    fn test_fuzz_bank_new() {
+        // decimal digits, the whitespace around a line, and characters that are digits in other bases
+        // or scripts but not battery ratings
+        const ALPHABET: &[char] = &['0', '1', '5', '9', ' ', '\t', '\r', 'a', 'F', '-', '½', '٣'];
+        let mut rng = Rng(0x8181_8191_1112_0111);
+        for _ in 0..20_000 {
+            let length = rng.below(40);
+            let input: String = (0..length)
+                .map(|_| ALPHABET[rng.below(ALPHABET.len())])
+                .collect();
+            // the parser must never panic, and only accept banks made of decimal digits
+            if let Ok(bank) = Bank::new(&input) {
+                assert!(!bank.batteries.is_empty());
+                assert!(bank.batteries.iter().all(|b| b.digit <= 9));
+            }
+        }
     }
 }
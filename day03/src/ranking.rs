@@ -0,0 +1,167 @@
+/// Indices of the `count` battery selections with the `n` highest distinct joltages, highest first
+///
+/// The batteries are rated below `base`, the base of the bank they're from.
+///
+/// Every selection has `count` digits, so distinct joltages are distinct digit sequences, and walking
+/// them with the highest digit first visits them from the highest joltage down. Each sequence is only
+/// picked at its leftmost positions, so it shows up once no matter how many selections spell it, and
+/// a digit is only tried where enough batteries follow it, so the walk never runs into a dead end.
+pub fn top_selections(digits: &[u8], base: u32, count: usize, n: usize) -> Vec<Vec<usize>> {
+    if count > digits.len() {
+        return Vec::new();
+    }
+    // one row of `base` entries per position, next[position * base + digit] being the first battery
+    // at or after position with that digit
+    let base = base as usize;
+    let mut next = vec![None; (digits.len() + 1) * base];
+    for position in (0..digits.len()).rev() {
+        next.copy_within(
+            (position + 1) * base..(position + 2) * base,
+            position * base,
+        );
+        next[position * base + usize::from(digits[position])] = Some(position);
+    }
+
+    let mut found = Vec::new();
+    let mut indices = Vec::with_capacity(count);
+    collect(&next, base, count, n, &mut indices, &mut found);
+    found
+}
+
+fn collect(
+    next: &[Option<usize>],
+    base: usize,
+    count: usize,
+    n: usize,
+    indices: &mut Vec<usize>,
+    found: &mut Vec<Vec<usize>>,
+) {
+    if found.len() == n {
+        return;
+    }
+    if indices.len() == count {
+        found.push(indices.clone());
+        return;
+    }
+    let start = indices.last().map_or(0, |&index| index + 1);
+    // leave room for the batteries still to pick after this one
+    let last_allowed = next.len() / base - 1 - (count - indices.len());
+    for digit in (0..base).rev() {
+        let Some(index) = next[start * base + digit].filter(|&index| index <= last_allowed) else {
+            continue;
+        };
+        indices.push(index);
+        collect(next, base, count, n, indices, found);
+        indices.pop();
+        if found.len() == n {
+            return;
+        }
+    }
+}
+
+/// Number of ways to pick batteries spelling out `target`, saturating at `u128::MAX`
+///
+/// Counted per prefix of the target while going over the batteries once, so it never lists the
+/// selections themselves.
+pub fn count_selections(digits: &[u8], target: &[u8]) -> u128 {
+    // ways[matched] is the number of ways to spell the first `matched` digits of the target so far
+    let mut ways = vec![0u128; target.len() + 1];
+    ways[0] = 1;
+    for &digit in digits {
+        for matched in (1..=target.len()).rev() {
+            if target[matched - 1] == digit {
+                ways[matched] = ways[matched].saturating_add(ways[matched - 1]);
+            }
+        }
+    }
+    ways[target.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::{Rng, all_selections, digits};
+
+    fn joltages(bank: &str, selections: &[Vec<usize>]) -> Vec<String> {
+        selections
+            .iter()
+            .map(|indices| indices.iter().map(|&index| &bank[index..=index]).collect())
+            .collect()
+    }
+
+    #[test]
+    fn test_top_selections() {
+        let bank = "818181911112111";
+        let top = top_selections(&digits(bank), 10, 2, 5);
+        assert_eq!(joltages(bank, &top), vec!["92", "91", "89", "88", "82"]);
+        // the leftmost batteries spelling each joltage
+        assert_eq!(top[1], vec![6, 7]);
+
+        // fewer distinct joltages than asked for
+        let top = top_selections(&digits("1111"), 10, 2, 5);
+        assert_eq!(top, vec![vec![0, 1]]);
+        assert_eq!(
+            top_selections(&digits("1234"), 10, 0, 5),
+            vec![Vec::<usize>::new()]
+        );
+        assert!(top_selections(&digits("1234"), 10, 5, 5).is_empty());
+        assert!(top_selections(&digits("1234"), 10, 2, 0).is_empty());
+        assert!(top_selections(&digits("1234"), 10, 0, 0).is_empty());
+
+        // ratings above 9 from banks in higher bases
+        let top = top_selections(&[35, 3, 10, 35, 12], 36, 2, 3);
+        assert_eq!(top, vec![vec![0, 3], vec![0, 4], vec![0, 2]]);
+        // and the table only as wide as the base for banks in lower ones
+        let top = top_selections(&[1, 0, 1, 1], 2, 2, 5);
+        assert_eq!(top, vec![vec![0, 2], vec![0, 1], vec![1, 2]]);
+    }
+
+    #[test]
+    fn test_count_selections() {
+        assert_eq!(count_selections(&digits("818181911112111"), &[9, 2]), 1);
+        // each 8 with any of the ones after it
+        assert_eq!(
+            count_selections(&digits("818181911112111"), &[8, 1]),
+            10 + 9 + 8
+        );
+        assert_eq!(count_selections(&digits("818181911112111"), &[1, 1]), 45);
+        assert_eq!(count_selections(&digits("1111"), &[1, 1]), 6);
+        assert_eq!(count_selections(&digits("1111"), &[]), 1);
+        assert_eq!(count_selections(&digits("1111"), &[2]), 0);
+        // 100 choose 50, far too many to list
+        let nines = vec![9; 100];
+        assert_eq!(
+            count_selections(&nines, &[9; 50]),
+            100891344545564193334812497256
+        );
+        assert_eq!(count_selections(&[9; 200], &[9; 100]), u128::MAX);
+    }
+
+    #[test]
+    fn test_matches_brute_force() {
+        let mut rng = Rng(0x7095_e1ec_7100_0047);
+        for _ in 0..500 {
+            let length = 1 + rng.below(10);
+            let digits: Vec<u8> = (0..length).map(|_| rng.below(4) as u8 * 3).collect();
+            let count = rng.below(length + 1);
+            let n = rng.below(8);
+
+            // every selection's digits, highest joltage first
+            let mut all = all_selections(&digits, count);
+            all.sort_unstable_by(|a, b| b.cmp(a));
+            let ties = all.iter().filter(|selection| **selection == all[0]).count();
+            all.dedup();
+            all.truncate(n);
+
+            let top = top_selections(&digits, 10, count, n);
+            let top_digits: Vec<Vec<u8>> = top
+                .iter()
+                .map(|indices| indices.iter().map(|&index| digits[index]).collect())
+                .collect();
+            assert_eq!(top_digits, all, "{digits:?}, top {n} of {count}");
+            if let Some(best) = top_digits.first() {
+                assert_eq!(count_selections(&digits, best), ties as u128);
+            }
+        }
+    }
+}
@@ -0,0 +1,354 @@
+use crate::Bank;
+use crate::joltage::Joltage;
+use std::fmt::Write;
+
+/// Batteries per line when no width is given
+pub const DEFAULT_WIDTH: usize = 50;
+
+const ANSI_ENABLED: &str = "\x1b[1;32m";
+const ANSI_DISABLED: &str = "\x1b[2m";
+const ANSI_RESET: &str = "\x1b[0m";
+
+/// SVG cell size in pixels
+const CELL_WIDTH: usize = 14;
+const CELL_HEIGHT: usize = 20;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Format {
+    /// Text with a `^` under every enabled battery
+    Plain,
+    /// Text with the enabled batteries highlighted by terminal colors
+    Ansi,
+    Html,
+    Svg,
+}
+
+impl std::str::FromStr for Format {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "plain" => Ok(Format::Plain),
+            "ansi" => Ok(Format::Ansi),
+            "html" => Ok(Format::Html),
+            "svg" => Ok(Format::Svg),
+            _ => Err(format!("Unknown render format: {s:?}")),
+        }
+    }
+}
+
+/// Joltage of the enabled batteries
+fn enabled_joltage(bank: &Bank) -> Joltage {
+    let digits: Vec<u8> = bank
+        .batteries
+        .iter()
+        .filter(|battery| battery.enabled)
+        .map(|battery| battery.digit)
+        .collect();
+    Joltage::from_digits_in(&digits, bank.alphabet.base())
+}
+
+/// Positions in `start..end` that get a label: the first one, then every multiple of 10 with room for it
+fn ruler_labels(start: usize, end: usize) -> Vec<usize> {
+    let mut labels = Vec::new();
+    let mut free = start;
+    for position in std::iter::once(start).chain((start.next_multiple_of(10)..end).step_by(10)) {
+        if position < free || position >= end {
+            continue;
+        }
+        labels.push(position);
+        // keep a space between labels
+        free = position + position.to_string().len() + 1;
+    }
+    labels
+}
+
+/// The labelled positions of `start..end` lined up above the batteries, cut off where the line ends
+fn ruler(start: usize, end: usize) -> String {
+    let mut line = vec![' '; end - start];
+    for position in ruler_labels(start, end) {
+        for (offset, c) in position.to_string().chars().enumerate() {
+            if let Some(cell) = line.get_mut(position - start + offset) {
+                *cell = c;
+            }
+        }
+    }
+    line.into_iter().collect::<String>().trim_end().to_string()
+}
+
+/// The bank wrapped at `width` batteries per line, each line under a ruler with the positions
+pub fn text(bank: &Bank, width: usize, color: bool) -> String {
+    assert!(width > 0, "Can't wrap a bank at 0 batteries");
+    let mut output = String::new();
+    for (row, chunk) in bank.batteries.chunks(width).enumerate() {
+        let start = row * width;
+        writeln!(output, "{}", ruler(start, start + chunk.len())).unwrap();
+        for battery in chunk {
+            let symbol = bank.alphabet.symbol(battery.digit);
+            match (color, battery.enabled) {
+                (false, _) => write!(output, "{symbol}"),
+                (true, true) => write!(output, "{ANSI_ENABLED}{symbol}{ANSI_RESET}"),
+                (true, false) => write!(output, "{ANSI_DISABLED}{symbol}{ANSI_RESET}"),
+            }
+            .unwrap();
+        }
+        writeln!(output).unwrap();
+        // without colors the enabled batteries are marked on a line of their own
+        if !color {
+            let markers: String = chunk
+                .iter()
+                .map(|battery| if battery.enabled { '^' } else { ' ' })
+                .collect();
+            writeln!(output, "{}", markers.trim_end()).unwrap();
+        }
+    }
+    output
+}
+
+/// `text` safe to put in HTML or SVG, custom alphabets can use any symbol
+fn escape(text: &str) -> String {
+    text.chars()
+        .map(|c| match c {
+            '<' => "&lt;".to_string(),
+            '>' => "&gt;".to_string(),
+            '&' => "&amp;".to_string(),
+            _ => c.to_string(),
+        })
+        .collect()
+}
+
+/// A `<pre>` block of the bank, with the enabled batteries in `<b>` tags
+pub fn html(bank: &Bank, width: usize) -> String {
+    assert!(width > 0, "Can't wrap a bank at 0 batteries");
+    let mut output = "<pre class=\"bank\">".to_string();
+    for (row, chunk) in bank.batteries.chunks(width).enumerate() {
+        let start = row * width;
+        writeln!(
+            output,
+            "<span class=\"ruler\">{}</span>",
+            ruler(start, start + chunk.len())
+        )
+        .unwrap();
+        for battery in chunk {
+            let symbol = escape(&bank.alphabet.symbol(battery.digit).to_string());
+            if battery.enabled {
+                write!(output, "<b>{symbol}</b>").unwrap();
+            } else {
+                write!(output, "{symbol}").unwrap();
+            }
+        }
+        writeln!(output).unwrap();
+    }
+    output.push_str("</pre>\n");
+    output
+}
+
+/// SVG elements drawing the bank as a grid of cells `width` wide, starting `top` pixels down
+///
+/// Returns the elements and the height they take.
+fn svg_cells(bank: &Bank, width: usize, top: usize) -> (String, usize) {
+    assert!(width > 0, "Can't wrap a bank at 0 batteries");
+    let mut output = String::new();
+    let mut y = top;
+    for (row, chunk) in bank.batteries.chunks(width).enumerate() {
+        let start = row * width;
+        for position in ruler_labels(start, start + chunk.len()) {
+            writeln!(
+                output,
+                "<text class=\"ruler\" x=\"{}\" y=\"{}\">{position}</text>",
+                (position - start) * CELL_WIDTH,
+                y + CELL_HEIGHT / 2
+            )
+            .unwrap();
+        }
+        y += CELL_HEIGHT / 2 + 2;
+        for (column, battery) in chunk.iter().enumerate() {
+            let class = if battery.enabled { "on" } else { "off" };
+            let x = column * CELL_WIDTH;
+            writeln!(
+                output,
+                "<rect class=\"{class}\" x=\"{x}\" y=\"{y}\" width=\"{CELL_WIDTH}\" height=\"{CELL_HEIGHT}\"/><text class=\"{class}\" x=\"{}\" y=\"{}\">{}</text>",
+                x + CELL_WIDTH / 2,
+                y + CELL_HEIGHT * 3 / 4,
+                escape(&bank.alphabet.symbol(battery.digit).to_string())
+            )
+            .unwrap();
+        }
+        y += CELL_HEIGHT;
+    }
+    (output, y - top)
+}
+
+/// Every bank with its joltage, then the total, as one plain or colored text, HTML page or SVG image
+///
+/// Joltages are written with `show`, so they can be in the base of the banks or in decimal.
+pub fn report(
+    banks: &[Bank],
+    format: Format,
+    width: usize,
+    show: impl Fn(&Joltage) -> String,
+) -> String {
+    let joltages: Vec<Joltage> = banks.iter().map(enabled_joltage).collect();
+    let total = show(&joltages.iter().sum());
+    let joltages: Vec<String> = joltages.iter().map(&show).collect();
+    let mut output = String::new();
+    match format {
+        Format::Plain | Format::Ansi => {
+            for (index, (bank, joltage)) in banks.iter().zip(&joltages).enumerate() {
+                writeln!(output, "Bank {index}: joltage {joltage}").unwrap();
+                output.push_str(&text(bank, width, format == Format::Ansi));
+            }
+            writeln!(output, "Total joltage: {total}").unwrap();
+        }
+        Format::Html => {
+            output.push_str(concat!(
+                "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>Battery banks</title>\n",
+                "<style>.bank b { color: #1a7f37; } .bank .ruler { color: #999; }</style>\n",
+                "</head>\n<body>\n"
+            ));
+            for (index, (bank, joltage)) in banks.iter().zip(&joltages).enumerate() {
+                writeln!(output, "<h2>Bank {index}: joltage {}</h2>", escape(joltage)).unwrap();
+                output.push_str(&html(bank, width));
+            }
+            writeln!(
+                output,
+                "<p>Total joltage: {}</p>\n</body>\n</html>",
+                escape(&total)
+            )
+            .unwrap();
+        }
+        Format::Svg => {
+            let mut body = String::new();
+            let mut y = 0;
+            for (index, (bank, joltage)) in banks.iter().zip(&joltages).enumerate() {
+                y += CELL_HEIGHT;
+                writeln!(
+                    body,
+                    "<text class=\"title\" x=\"0\" y=\"{y}\">Bank {index}: joltage {}</text>",
+                    escape(joltage)
+                )
+                .unwrap();
+                let (cells, height) = svg_cells(bank, width, y);
+                body.push_str(&cells);
+                y += height + CELL_HEIGHT / 2;
+            }
+            y += CELL_HEIGHT;
+            writeln!(
+                body,
+                "<text class=\"title\" x=\"0\" y=\"{y}\">Total joltage: {}</text>",
+                escape(&total)
+            )
+            .unwrap();
+            y += CELL_HEIGHT / 2;
+
+            let columns = banks
+                .iter()
+                .map(|bank| bank.batteries.len().min(width))
+                .max()
+                .unwrap_or(0);
+            // wide enough for the titles as well as the cells
+            let svg_width = (columns * CELL_WIDTH).max(40 * CELL_WIDTH / 2);
+            writeln!(
+                output,
+                "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{svg_width}\" height=\"{y}\" font-family=\"monospace\" font-size=\"12\">"
+            )
+            .unwrap();
+            output.push_str(concat!(
+                "<style>rect.on { fill: #1a7f37; } rect.off { fill: #eee; stroke: #fff; } ",
+                "text.on { fill: #fff; text-anchor: middle; } text.off { fill: #666; text-anchor: middle; } ",
+                "text.ruler { fill: #999; font-size: 9px; } text.title { font-weight: bold; }</style>\n"
+            ));
+            output.push_str(&body);
+            output.push_str("</svg>\n");
+        }
+    }
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Mode;
+
+    fn bank(line: &str, count: usize) -> Bank {
+        let mut bank = Bank::new(line).unwrap();
+        let selection = bank.select(count, &Mode::Max).unwrap();
+        bank.apply(&selection);
+        bank
+    }
+
+    #[test]
+    fn test_ruler() {
+        assert_eq!(ruler(0, 25), "0         10        20");
+        assert_eq!(ruler(5, 12), "5    10");
+        // labels are cut off at the end of the line
+        assert_eq!(ruler(95, 101), "95   1");
+        // and skipped when they'd run into the one before
+        assert_eq!(ruler(99, 111), "99         1");
+        assert_eq!(ruler(1, 9), "1");
+    }
+
+    #[test]
+    fn test_plain_text() {
+        let expected = "\
+0         10
+818181911112
+^ ^ ^ ^^^^^^
+12
+111
+^^^
+";
+        assert_eq!(text(&bank("818181911112111", 12), 12, false), expected);
+        assert_eq!(
+            text(&bank("987654321111111", 2), 100, false),
+            "0         10\n987654321111111\n^^\n"
+        );
+    }
+
+    #[test]
+    fn test_ansi_text() {
+        let rendered = text(&bank("1291", 2), 10, true);
+        assert_eq!(
+            rendered,
+            "0\n\x1b[2m1\x1b[0m\x1b[2m2\x1b[0m\x1b[1;32m9\x1b[0m\x1b[1;32m1\x1b[0m\n"
+        );
+        // no marker line, the colors already show which batteries are enabled
+        assert_eq!(rendered.lines().count(), 2);
+    }
+
+    #[test]
+    fn test_html() {
+        assert_eq!(
+            html(&bank("1291", 2), 3),
+            "<pre class=\"bank\"><span class=\"ruler\">0</span>\n12<b>9</b>\n<span class=\"ruler\">3</span>\n<b>1</b>\n</pre>\n"
+        );
+    }
+
+    #[test]
+    fn test_report() {
+        let banks = [bank("818181911112111", 12), bank("1291", 2)];
+        let plain = report(&banks, Format::Plain, 50, Joltage::to_string);
+        assert!(plain.starts_with("Bank 0: joltage 888911112111\n0         10\n"));
+        assert!(plain.contains("Bank 1: joltage 91\n0\n1291\n  ^^\n"));
+        assert!(plain.ends_with("Total joltage: 888911112202\n"));
+
+        let page = report(&banks, Format::Html, 50, Joltage::to_string);
+        assert!(page.starts_with("<!DOCTYPE html>"));
+        assert!(page.contains("<h2>Bank 1: joltage 91</h2>\n<pre class=\"bank\">"));
+        assert!(page.ends_with("<p>Total joltage: 888911112202</p>\n</body>\n</html>\n"));
+
+        let svg = report(&banks, Format::Svg, 10, Joltage::to_string);
+        assert!(svg.starts_with("<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"280\""));
+        assert!(svg.ends_with("</svg>\n"));
+        // one cell per battery, enabled or not
+        assert_eq!(svg.matches("<rect class=\"on\"").count(), 12 + 2);
+        assert_eq!(svg.matches("<rect ").count(), 15 + 4);
+        assert!(svg.contains(">Total joltage: 888911112202</text>"));
+    }
+
+    #[test]
+    fn test_parse_format() {
+        assert_eq!("svg".parse(), Ok(Format::Svg));
+        assert!("pdf".parse::<Format>().is_err());
+    }
+}